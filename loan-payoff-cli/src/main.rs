@@ -1,68 +1,597 @@
-use loan_payoff::{self, Loan};
-use log;
+use loan_payoff::{
+	self, Allocation, AllocationRule, ExtraPaymentRange, ExtraPayments, FixedOrder, Loan, LumpSum,
+	Money, PaymentOnRateChange, PayoffObserver, PayoffResult, PayoffStrategy, PromotionDeadlines,
+	RateChange, RateSchedule, Share, SilentObserver, Split, ValidationPolicy,
+};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
 use std::env;
 use std::error::Error;
 use std::ffi::OsString;
 use std::fs::File;
 use std::process;
 
+struct Args {
+	file_path: OsString,
+	extra_amount: Money,
+	/// CSV of extra payment ranges, replaces `extra_amount`
+	extra_schedule: Option<OsString>,
+	/// Annual growth of `extra_amount`
+	extra_growth: Option<f64>,
+	/// CSV of lump sums paid on top of the extra payments
+	lump_sums: Option<OsString>,
+	/// CSV of shares of the extra set aside for particular loans
+	allocation: Option<OsString>,
+	/// Also search splits of the extra in steps of this fraction
+	split_step: Option<f64>,
+	/// CSV of rate changes for variable-rate loans
+	rate_changes: Option<OsString>,
+	/// Find the smallest extra amount that pays everything off by this period, replaces `extra_amount`
+	debt_free_by: Option<i64>,
+	/// How closely each loan's payment has to match its other terms
+	validation: ValidationPolicy,
+	show_schedule: bool,
+	strategy: String,
+}
+
 fn main() {
 	simple_logger::init_with_level(log::Level::Info).unwrap();
 
-	let file_path = match env::args_os().nth(1) {
-		None => {
-			log::error!("expected at least 1 argument (file path, extra amount), but got none");
+	let args = match parse_args(env::args_os().skip(1)) {
+		Err(err) => {
+			log::error!("{}", err);
 			process::exit(1);
 		}
-		Some(file_path) => file_path,
+		Ok(args) => args,
 	};
 
-	let extra_amount = match env::args().nth(2) {
+	if let Err(err) = process_loans(args) {
+		println!("error running example: {}", err);
+		process::exit(1);
+	}
+}
+
+// Flags can appear anywhere, everything else is positional
+fn parse_args(args: impl Iterator<Item = OsString>) -> Result<Args, String> {
+	let mut show_schedule = false;
+	let mut strategy = "optimal".to_owned();
+	let mut extra_schedule = None;
+	let mut extra_growth = None;
+	let mut lump_sums = None;
+	let mut allocation = None;
+	let mut split_step = None;
+	let mut rate_changes = None;
+	let mut debt_free_by = None;
+	let mut validation = ValidationPolicy::Strict;
+	let mut positional_args = Vec::new();
+	let mut args = args;
+	while let Some(arg) = args.next() {
+		match arg.to_str() {
+			Some("--schedule") => show_schedule = true,
+			Some("--strategy") => {
+				strategy = args
+					.next()
+					.map(|s| s.to_string_lossy().into_owned())
+					.ok_or("expected a strategy name after --strategy")?;
+			}
+			Some("--extra-schedule") => {
+				extra_schedule = Some(
+					args.next()
+						.ok_or("expected a file path after --extra-schedule")?,
+				);
+			}
+			Some("--lump-sums") => {
+				lump_sums = Some(
+					args.next()
+						.ok_or("expected a file path after --lump-sums")?,
+				);
+			}
+			Some("--allocation") => {
+				allocation = Some(
+					args.next()
+						.ok_or("expected a file path after --allocation")?,
+				);
+			}
+			Some("--rate-changes") => {
+				rate_changes = Some(
+					args.next()
+						.ok_or("expected a file path after --rate-changes")?,
+				);
+			}
+			Some("--split-step") => {
+				let step = args
+					.next()
+					.map(|s| s.to_string_lossy().into_owned())
+					.ok_or("expected a fraction after --split-step")?;
+				split_step = Some(
+					step.parse::<f64>()
+						.ok()
+						.filter(|&step| step > 0.0 && step <= 1.0)
+						.ok_or(format!("'{}' is not a fraction from 0 to 1", step))?,
+				);
+			}
+			Some("--debt-free-by") => {
+				let period = args
+					.next()
+					.map(|s| s.to_string_lossy().into_owned())
+					.ok_or("expected a period after --debt-free-by")?;
+				debt_free_by = Some(
+					period
+						.parse::<i64>()
+						.ok()
+						.filter(|&period| period > 0)
+						.ok_or(format!("'{}' is not a period", period))?,
+				);
+			}
+			Some("--validation") => {
+				let policy = args
+					.next()
+					.map(|s| s.to_string_lossy().into_owned())
+					.ok_or("expected a validation policy after --validation")?;
+				validation = parse_validation_policy(&policy)?;
+			}
+			Some("--extra-growth") => {
+				let growth = args
+					.next()
+					.map(|s| s.to_string_lossy().into_owned())
+					.ok_or("expected an annual growth rate after --extra-growth")?;
+				extra_growth = Some(
+					growth
+						.parse::<f64>()
+						.map_err(|_| format!("'{}' is not a valid growth rate", growth))?,
+				);
+			}
+			_ => positional_args.push(arg),
+		}
+	}
+
+	let mut positional_args = positional_args.into_iter();
+	let file_path = positional_args
+		.next()
+		.ok_or("expected at least 1 argument (file path, extra amount), but got none")?;
+
+	let extra_amount = match positional_args
+		.next()
+		.map(|arg| arg.to_string_lossy().into_owned())
+	{
 		None => {
 			log::trace!("no amount supplied for second argument, using default 100.00");
-			100.0
+			Money::from_f64(100.0)
 		}
-		Some(extra_amount) => match extra_amount.parse::<f64>() {
-			Err(_) => {
-				log::error!("could not parse entered value '{}' to f64", extra_amount);
-				process::exit(1);
-			}
-			Ok(extra_amount) => loan_payoff::round_to_currency(extra_amount),
-		},
+		Some(extra_amount) => extra_amount
+			.parse::<Money>()
+			.map_err(|err| err.to_string())?,
 	};
 
-	if let Err(err) = process_loans(file_path, extra_amount) {
-		println!("error running example: {}", err);
-		process::exit(1);
+	if extra_schedule.is_some() && extra_growth.is_some() {
+		return Err("--extra-schedule and --extra-growth can't be used together".to_owned());
 	}
+	if debt_free_by.is_some() && (extra_schedule.is_some() || extra_growth.is_some()) {
+		return Err(
+			"--debt-free-by finds a constant extra amount, it can't be used with --extra-schedule or --extra-growth"
+				.to_owned(),
+		);
+	}
+
+	Ok(Args {
+		file_path,
+		extra_amount,
+		extra_schedule,
+		extra_growth,
+		lump_sums,
+		allocation,
+		split_step,
+		rate_changes,
+		debt_free_by,
+		validation,
+		show_schedule,
+		strategy,
+	})
 }
 
-fn process_loans(file_path: OsString, extra_amount: f64) -> Result<(), Box<dyn Error>> {
+fn parse_validation_policy(policy: &str) -> Result<ValidationPolicy, String> {
+	match policy {
+		"strict" => Ok(ValidationPolicy::Strict),
+		"trust-statement" => Ok(ValidationPolicy::TrustStatement),
+		_ => match policy.strip_prefix("tolerance:") {
+			Some(tolerance) => tolerance
+				.parse::<Money>()
+				.ok()
+				.filter(|tolerance| !tolerance.is_negative())
+				.map(ValidationPolicy::Tolerance)
+				.ok_or(format!("'{}' is not a valid tolerance", tolerance)),
+			None => Err(format!(
+				"unknown validation policy '{}', expected one of strict, tolerance:<amount> or trust-statement",
+				policy
+			)),
+		},
+	}
+}
+
+/// Returns `None` for the optimal strategy, which searches the orderings instead
+fn build_strategy(name: &str, loans: &[&Loan]) -> Result<Option<Box<dyn PayoffStrategy>>, String> {
+	let strategy: Box<dyn PayoffStrategy> = match name {
+		"optimal" => return Ok(None),
+		"snowball" => Box::new(loan_payoff::DebtSnowball),
+		"avalanche" => Box::new(loan_payoff::DebtAvalanche),
+		"highest-payment" => Box::new(loan_payoff::HighestPaymentFirst),
+		_ => match name.strip_prefix("fixed:") {
+			Some(names) => {
+				let ordering = names
+					.split(',')
+					.map(|name| {
+						loans
+							.iter()
+							.position(|l| l.name == name)
+							.ok_or(format!("no loan named '{}'", name))
+					})
+					.collect::<Result<Vec<_>, _>>()?;
+				Box::new(FixedOrder(ordering))
+			}
+			None => {
+				return Err(format!(
+					"unknown strategy '{}', expected one of optimal, snowball, avalanche, highest-payment or fixed:<name>,<name>,...",
+					name
+				))
+			}
+		},
+	};
+	Ok(Some(strategy))
+}
+
+fn process_loans(args: Args) -> Result<(), Box<dyn Error>> {
 	let mut loans = Vec::new();
-	let file = File::open(file_path)?;
+	let file = File::open(args.file_path)?;
 	let mut rdr = csv::Reader::from_reader(file);
 	for result in rdr.deserialize() {
-		let loan: Loan = result?;
-		println!("Read {}", loan);
+		let mut loan: Loan = result?;
+		loan.validation_policy = args.validation;
 		loans.push(loan);
 	}
+	if let Some(path) = &args.rate_changes {
+		read_rate_changes(path, &mut loans)?;
+	}
+	let mut invalid = 0;
+	for loan in loans.iter() {
+		println!("Read {}", loan);
+		if let Err(issues) = loan.validate() {
+			invalid += 1;
+			for issue in issues.iter() {
+				println!("{} - {}", loan.name, issue);
+			}
+		}
+	}
+	if invalid > 0 {
+		return Err(format!("{} of the loans are invalid", invalid).into());
+	}
 
-	let optimal_payoff =
-		loan_payoff::pay_loans_all_orderings(&loans.iter().map(|l| l).collect(), extra_amount)
-			.expect("Failed to pay loans");
-
-	println!(
-		"Best ordering = {}, with savings ${}, is debt snowball {}, savings over debt snowball ${}",
-		optimal_payoff
-			.ordering
-			.iter()
-			.map(|&i| loans[i].name.as_ref())
-			.collect::<Vec<_>>()
-			.join(" -> "),
-		optimal_payoff.savings,
-		optimal_payoff.is_debt_snowball,
-		optimal_payoff.savings_over_debt_snowball
-	);
+	let loans: Vec<&Loan> = loans.iter().collect();
+	let allocation = match &args.allocation {
+		Some(path) => read_allocation(path, &loans)?,
+		None => Allocation::default(),
+	};
+	let strategy = build_strategy(&args.strategy, &loans)?.map(|strategy| {
+		if allocation.is_empty() {
+			strategy
+		} else {
+			Box::new(Split {
+				allocation: allocation.clone(),
+				then: strategy,
+			})
+		}
+	});
+	let mut allocations = match args.split_step {
+		Some(step) => Allocation::percentage_splits(loans.len(), step),
+		None => Vec::new(),
+	};
+	if !allocations.contains(&allocation) {
+		allocations.insert(0, allocation);
+	}
+	let lump_sums = match &args.lump_sums {
+		Some(path) => read_csv::<LumpSum>(path)?,
+		None => Vec::new(),
+	};
 
+	let extra_amount = match args.debt_free_by {
+		Some(target_period) => {
+			let required =
+				loan_payoff::required_extra_payment_with(&loans, target_period, |extra| {
+					let extra_payments =
+						ExtraPayments::constant(extra).with_lump_sums(lump_sums.clone());
+					match &strategy {
+						None => loan_payoff::optimal_payoff_with_allocations(
+							&loans,
+							extra_payments,
+							&allocations,
+							&mut SilentObserver,
+						)
+						.map(|optimal_payoff| optimal_payoff.best),
+						Some(strategy) => loan_payoff::pay_loans_with_strategy(
+							&loans,
+							extra_payments,
+							strategy.as_ref(),
+						),
+					}
+				})?;
+			println!(
+				"Extra of ${} pays everything off by period {}, in period {}",
+				required.extra_amount, target_period, required.result.total_periods
+			);
+			required.extra_amount
+		}
+		None => args.extra_amount,
+	};
+	let extra_payments = match (&args.extra_schedule, args.extra_growth) {
+		(Some(path), _) => ExtraPayments::ranges(read_csv::<ExtraPaymentRange>(path)?),
+		(None, Some(growth)) => ExtraPayments::growing(extra_amount, growth),
+		(None, None) => ExtraPayments::constant(extra_amount),
+	};
+	let extra_payments = extra_payments.with_lump_sums(lump_sums);
+	let mut observer = PrintingObserver {
+		extra_payments: extra_payments.clone(),
+	};
+	let (result, strategy) = match strategy {
+		None => {
+			let optimal_payoff = loan_payoff::optimal_payoff_with_allocations(
+				&loans,
+				extra_payments.clone(),
+				&allocations,
+				&mut observer,
+			)?;
+
+			println!(
+				"Best ordering = {}, with savings ${}, is debt snowball {}, savings over debt snowball ${}",
+				ordering_names(&loans, &optimal_payoff.best.ordering),
+				optimal_payoff.best.savings_total,
+				optimal_payoff.best.is_debt_snowball,
+				optimal_payoff.savings_over_debt_snowball
+			);
+			let mut strategy: Box<dyn PayoffStrategy> = Box::new(Split {
+				allocation: optimal_payoff.best.allocation.clone(),
+				then: Box::new(FixedOrder(optimal_payoff.best.ordering.clone())),
+			});
+			if optimal_payoff.best.promotion_deadlines {
+				strategy = Box::new(PromotionDeadlines(strategy));
+			}
+			(optimal_payoff.best, strategy)
+		}
+		Some(strategy) => {
+			let result = loan_payoff::pay_loans_with_observer(
+				&loans,
+				extra_payments.clone(),
+				strategy.as_ref(),
+				&mut observer,
+			)?;
+
+			println!(
+				"Strategy {} starts with ordering = {}, with savings ${}",
+				args.strategy,
+				ordering_names(&loans, &result.ordering),
+				result.savings_total
+			);
+			(result, strategy)
+		}
+	};
+
+	if !result.allocation.is_empty() {
+		println!(
+			"Extra split = {}",
+			allocation_names(&loans, &result.allocation)
+		);
+	}
+	if result.promotion_deadlines {
+		println!("Deferred-interest promotions are cleared just before they end");
+	}
+	for &i in result.ordering.iter() {
+		println!(
+			"{} - paid off in period {}, saved ${}",
+			loans[i].name,
+			result.payoff_periods[i],
+			result.savings(i)
+		);
+		// only loans that ran to their last payment have one, a few cents either way is the rounding
+		let adjustment = result.final_payment_adjustments[i];
+		if adjustment.abs() > Money::from_cents(5) {
+			println!(
+				"{} - the last payment in period {} was ${} instead of ${}",
+				loans[i].name,
+				result.payoff_periods[i],
+				loans[i].payment_amount + adjustment,
+				loans[i].payment_amount
+			);
+		}
+		if result.fee_costs[i].is_positive() {
+			println!("{} - fees ${}", loans[i].name, result.fee_costs[i]);
+		}
+		if result.penalty_costs[i].is_positive() {
+			println!(
+				"{} - prepayment penalty ${}",
+				loans[i].name, result.penalty_costs[i]
+			);
+		}
+		if result.pmi_periods[i] > 0 {
+			println!(
+				"{} - PMI stops after period {}, ${} of PMI",
+				loans[i].name, result.pmi_periods[i], result.pmi_costs[i]
+			);
+		}
+	}
+	println!("Total periods={}", result.total_periods);
+	if result.periods_per_year != 12 {
+		println!(
+			"Some loans are paid more often than monthly, there are {} periods a year",
+			result.periods_per_year
+		);
+	}
+
+	if args.show_schedule {
+		let schedule =
+			loan_payoff::amortization_schedule(&loans, extra_payments, strategy.as_ref())?;
+		print_schedule(&loans, &schedule);
+	}
+
+	Ok(())
+}
+
+fn read_csv<T: DeserializeOwned>(path: &OsString) -> Result<Vec<T>, Box<dyn Error>> {
+	let file = File::open(path)?;
+	let mut rdr = csv::Reader::from_reader(file);
+	let rows = rdr.deserialize().collect::<Result<Vec<T>, _>>()?;
+	Ok(rows)
+}
+
+/// A row of the allocation CSV, with either a `percentage` or an `amount`
+#[derive(Deserialize)]
+struct AllocationRow {
+	loan: String,
+	percentage: Option<f64>,
+	amount: Option<Money>,
+}
+
+fn read_allocation(path: &OsString, loans: &[&Loan]) -> Result<Allocation, Box<dyn Error>> {
+	let rules = read_csv::<AllocationRow>(path)?
+		.into_iter()
+		.map(|row| {
+			let loan = loans
+				.iter()
+				.position(|l| l.name == row.loan)
+				.ok_or(format!("no loan named '{}'", row.loan))?;
+			let share = match (row.percentage, row.amount) {
+				(Some(percentage), None) => Share::Percentage(percentage),
+				(None, Some(amount)) => Share::Fixed(amount),
+				_ => {
+					return Err(format!(
+						"allocation for '{}' needs either a percentage or an amount",
+						row.loan
+					))
+				}
+			};
+			Ok(AllocationRule { loan, share })
+		})
+		.collect::<Result<Vec<_>, String>>()?;
+	Ok(Allocation::new(rules))
+}
+
+/// A row of the rate changes CSV, `cap`, `floor` and `keep_payment` apply to the whole loan when given
+#[derive(Deserialize)]
+struct RateChangeRow {
+	loan: String,
+	payment: i64,
+	apr: f64,
+	cap: Option<f64>,
+	floor: Option<f64>,
+	keep_payment: Option<bool>,
+}
+
+fn read_rate_changes(path: &OsString, loans: &mut [Loan]) -> Result<(), Box<dyn Error>> {
+	for row in read_csv::<RateChangeRow>(path)? {
+		let loan = loans
+			.iter_mut()
+			.find(|l| l.name == row.loan)
+			.ok_or(format!("no loan named '{}'", row.loan))?;
+		let schedule = loan.rate_schedule.get_or_insert_with(RateSchedule::default);
+		schedule.changes.push(RateChange {
+			payment: row.payment,
+			rate: row.apr / 12.0,
+		});
+		if let Some(cap) = row.cap {
+			schedule.cap = Some(cap / 12.0);
+		}
+		if let Some(floor) = row.floor {
+			schedule.floor = Some(floor / 12.0);
+		}
+		if let Some(keep_payment) = row.keep_payment {
+			schedule.on_change = if keep_payment {
+				PaymentOnRateChange::KeepPayment
+			} else {
+				PaymentOnRateChange::Reamortize
+			};
+		}
+	}
 	Ok(())
 }
+
+fn allocation_names(loans: &[&Loan], allocation: &Allocation) -> String {
+	allocation
+		.rules
+		.iter()
+		.map(|rule| match rule.share {
+			Share::Percentage(p) => format!("{}% to {}", p * 100.0, loans[rule.loan].name),
+			Share::Fixed(amount) => format!("${} to {}", amount, loans[rule.loan].name),
+		})
+		.collect::<Vec<_>>()
+		.join(", ")
+}
+
+fn ordering_names(loans: &[&Loan], ordering: &[usize]) -> String {
+	ordering
+		.iter()
+		.map(|&i| loans[i].name.as_ref())
+		.collect::<Vec<_>>()
+		.join(" -> ")
+}
+
+/// Prints a summary of every ordering as it is tried
+struct PrintingObserver {
+	extra_payments: ExtraPayments,
+}
+
+impl PayoffObserver for PrintingObserver {
+	fn ordering_paid_off(&mut self, loans: &[&Loan], result: &PayoffResult) {
+		println!("{}", ordering_names(loans, &result.ordering));
+		println!("EXPECTED=${}", result.expected_costs_total);
+		println!("ACTUAL=${}", result.actual_costs_total);
+		println!(
+			"By paying an extra {}, you saved ${}",
+			self.extra_payments, result.savings_total
+		);
+		println!("Is debt snowball {}", result.is_debt_snowball);
+		println!("Total periods={}", result.total_periods);
+	}
+}
+
+/// Mortgages add PMI and escrow columns, so the total matches the statement
+fn print_schedule(loans: &[&Loan], schedule: &loan_payoff::Schedule) {
+	let mortgages = loans.iter().any(|l| l.mortgage.is_some());
+	let fees = loans.iter().any(|l| !l.fees.is_empty());
+	print!(
+		"{:>6}  {:<20} {:>12} {:>10} {:>10} {:>10} {:>12} {:>12}",
+		"Period", "Loan", "Start", "Interest", "Payment", "Extra", "End", "Total Int."
+	);
+	if mortgages {
+		print!(" {:>8} {:>8}", "PMI", "Escrow");
+	}
+	if fees {
+		print!(" {:>8}", "Fees");
+	}
+	if mortgages || fees {
+		print!(" {:>10}", "Statement");
+	}
+	println!();
+	for period in schedule.periods.iter() {
+		for entry in period.entries.iter() {
+			print!(
+				"{:>6}  {:<20} {:>12} {:>10} {:>10} {:>10} {:>12} {:>12}",
+				period.period,
+				loans[entry.loan].name,
+				entry.starting_balance,
+				entry.interest,
+				entry.scheduled_payment,
+				entry.extra_payment,
+				entry.ending_balance,
+				entry.cumulative_interest
+			);
+			if mortgages {
+				print!(" {:>8} {:>8}", entry.pmi, entry.escrow);
+			}
+			if fees {
+				print!(" {:>8}", entry.fees);
+			}
+			if mortgages || fees {
+				print!(" {:>10}", entry.total_payment());
+			}
+			println!();
+		}
+	}
+	println!("Total interest=${}", schedule.total_interest());
+}
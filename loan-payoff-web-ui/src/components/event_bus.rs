@@ -1,4 +1,4 @@
-use loan_payoff::{self, Loan};
+use loan_payoff::{Loan, LoanTerm, Money, Rate};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use yew_agent::{Agent, AgentLink, Context, HandlerId};
@@ -11,9 +11,13 @@ pub enum Request {
 	Bump, // just used to get initial load
 	DeleteLoan(usize),
 	UpdateInitialValue(f64, usize),
+	/// Annual percentage rate, 7.5 for 7.5%
 	UpdateInterestRate(f64, usize),
 	UpdateName(String, usize),
 	UpdateNumberOfPayments(i64, usize),
+	UpdatePaymentAmount(f64, usize),
+	/// Which of the loan's terms is worked out from the others
+	UpdateSolveFor(LoanTerm, usize),
 }
 
 pub struct EventBus {
@@ -48,6 +52,8 @@ impl Agent for EventBus {
 						..Default::default()
 					},
 					key: self.current_key,
+					solve_for: LoanTerm::PaymentAmount,
+					solved: false,
 				};
 				self.current_key += 1;
 				self.loans.push(new_loan);
@@ -57,40 +63,30 @@ impl Agent for EventBus {
 			}
 			Request::Bump => { /* just responds below */ }
 			Request::UpdateInitialValue(new_amount, index) => {
-				self.loans[index].loan.initial_value = new_amount;
-				let calculated_payment_amount = loan_payoff::round_to_currency(
-					self.loans[index].loan.calculate_payment_amount(),
-				);
-				if calculated_payment_amount > 0.0 {
-					self.loans[index].loan.payment_amount = calculated_payment_amount;
-				} else {
-					// TODO: set error flag and mark row as invalid
-				}
+				self.loans[index].loan.initial_value = Money::from_f64(new_amount);
+				self.solve(index);
 			}
 			Request::UpdateInterestRate(new_rate, index) => {
-				self.loans[index].loan.rate = new_rate;
-				let calculated_payment_amount = loan_payoff::round_to_currency(
-					self.loans[index].loan.calculate_payment_amount(),
-				);
-				if calculated_payment_amount > 0.0 {
-					self.loans[index].loan.payment_amount = calculated_payment_amount;
-				} else {
-					// TODO: set error flag and mark row as invalid
-				}
+				let compounding = self.loans[index].loan.compounding;
+				self.loans[index]
+					.loan
+					.set_rate(Rate::from_apr(new_rate / 100.0, compounding));
+				self.solve(index);
 			}
 			Request::UpdateName(new_name, index) => {
 				self.loans[index].loan.name = new_name;
 			}
 			Request::UpdateNumberOfPayments(new_number_of_payments, index) => {
 				self.loans[index].loan.number_of_payments = new_number_of_payments;
-				let calculated_payment_amount = loan_payoff::round_to_currency(
-					self.loans[index].loan.calculate_payment_amount(),
-				);
-				if calculated_payment_amount > 0.0 {
-					self.loans[index].loan.payment_amount = calculated_payment_amount;
-				} else {
-					// TODO: set error flag and mark row as invalid
-				}
+				self.solve(index);
+			}
+			Request::UpdatePaymentAmount(new_amount, index) => {
+				self.loans[index].loan.payment_amount = Money::from_f64(new_amount);
+				self.solve(index);
+			}
+			Request::UpdateSolveFor(term, index) => {
+				self.loans[index].solve_for = term;
+				self.solve(index);
 			}
 		}
 
@@ -107,3 +103,11 @@ impl Agent for EventBus {
 		self.subscribers.remove(&id);
 	}
 }
+
+impl EventBus {
+	// Works out the term the loan solves for from the other three, which may not fit together yet while typing
+	fn solve(&mut self, index: usize) {
+		let view_model = &mut self.loans[index];
+		view_model.solved = view_model.loan.solve_for(view_model.solve_for).is_ok();
+	}
+}
@@ -1,7 +1,7 @@
 use super::event_bus::{EventBus, Request};
-use loan_payoff::{self, Loan};
+use loan_payoff::{self, Loan, LoanTerm};
 use std::fmt::{Debug, Display};
-use web_sys::{HtmlInputElement, InputEvent};
+use web_sys::{Event, HtmlInputElement, HtmlSelectElement, InputEvent};
 use yew::prelude::*;
 use yew_agent::{Dispatched, Dispatcher};
 
@@ -11,8 +11,17 @@ pub enum LoanMsg {
 	UpdateInterestRate(f64, usize),
 	UpdateName(String, usize),
 	UpdateNumberOfPayments(i64, usize),
+	UpdatePaymentAmount(f64, usize),
+	UpdateSolveFor(String, usize),
 }
 
+const SOLVE_FOR_CHOICES: [(LoanTerm, &str); 4] = [
+	(LoanTerm::InitialValue, "Loan Amount"),
+	(LoanTerm::Rate, "Interest Rate"),
+	(LoanTerm::NumberOfPayments, "Number of Payments"),
+	(LoanTerm::PaymentAmount, "Monthly Payment"),
+];
+
 pub struct LoanRow {
 	event_bus: Dispatcher<EventBus>,
 }
@@ -20,6 +29,9 @@ pub struct LoanRow {
 #[derive(Clone, PartialEq, Properties)]
 pub struct LoanProps {
 	pub loan: Loan,
+	/// The term worked out from the others, which is shown instead of being edited
+	pub solve_for: LoanTerm,
+	pub solved: bool,
 	pub index: usize,
 	pub show_validation_errors: bool,
 }
@@ -61,22 +73,43 @@ impl Component for LoanRow {
 				));
 				true
 			}
+			LoanMsg::UpdatePaymentAmount(new_amount, index) => {
+				self.event_bus
+					.send(Request::UpdatePaymentAmount(new_amount, index));
+				true
+			}
+			LoanMsg::UpdateSolveFor(id, index) => {
+				if let Some(&(term, _)) = SOLVE_FOR_CHOICES
+					.iter()
+					.find(|(term, _)| term.to_string() == id)
+				{
+					self.event_bus.send(Request::UpdateSolveFor(term, index));
+				}
+				true
+			}
 		}
 	}
 
 	fn view(&self, ctx: &Context<Self>) -> Html {
 		let link = ctx.link();
-		let mut calculated_payment_amount = ctx.props().loan.calculate_payment_amount();
-		calculated_payment_amount = loan_payoff::round_to_currency(calculated_payment_amount);
+		let loan = &ctx.props().loan;
+		let solve_for = ctx.props().solve_for;
 		let index = ctx.props().index;
 		let show_validation_errors = ctx.props().show_validation_errors;
-		let input_class = if show_validation_errors
-			&& (calculated_payment_amount.is_nan() || calculated_payment_amount.is_infinite())
-		{
+		let input_class = if show_validation_errors && !ctx.props().solved {
 			"invalid".clone()
 		} else {
 			"".clone()
 		};
+		// the solved term is only shown, and left blank until the other terms fit together
+		let solved_value = |value: String| {
+			let value = if ctx.props().solved {
+				value
+			} else {
+				"".to_owned()
+			};
+			html! { <span>{ value }</span> }
+		};
 		let name_class = if show_validation_errors && ctx.props().loan.name.is_empty() {
 			"invalid".clone()
 		} else {
@@ -101,41 +134,81 @@ impl Component for LoanRow {
 					</div>
 				</div>
 				<div class="col l2 s12">
-					<InputNumber<f64>
-						value={ctx.props().loan.initial_value}
-						{index}
-						{input_class}
-						id="loan_initial_value"
-						label="Loan Amount"
-						request={link.callback(move |new_val: f64| LoanMsg::UpdateInitialValue(new_val, index))}
-					/>
+					if solve_for == LoanTerm::InitialValue {
+						<span class="hide-on-med-and-up">{ "Loan Amount: " }</span>
+						{ solved_value(loan.initial_value.to_string()) }
+					} else {
+						<InputNumber<f64>
+							value={loan.initial_value.to_f64()}
+							{index}
+							{input_class}
+							id="loan_initial_value"
+							label="Loan Amount"
+							request={link.callback(move |new_val: f64| LoanMsg::UpdateInitialValue(new_val, index))}
+						/>
+					}
 				</div>
 				<div class="col l2 s12">
-					<InputNumber<f64>
-						value={loan_payoff::round_to_decimals(ctx.props().loan.rate * 12.0 * 100.0, 1)}
-						step=".1"
-						{index}
-						{input_class}
-						id="loan_interest_rate"
-						label="Interest Rate"
-						request={link.callback(move |new_val: f64| LoanMsg::UpdateInterestRate(new_val/12.0/100.0, index))}
-					/>
+					if solve_for == LoanTerm::Rate {
+						<span class="hide-on-med-and-up">{ "Interest Rate: " }</span>
+						{ solved_value(format!("{}%", loan_payoff::round_to_decimals(loan.annual_rate().apr() * 100.0, 3))) }
+					} else {
+						<InputNumber<f64>
+							value={loan_payoff::round_to_decimals(loan.annual_rate().apr() * 100.0, 1)}
+							step=".1"
+							{index}
+							{input_class}
+							id="loan_interest_rate"
+							label="Interest Rate"
+							request={link.callback(move |new_val: f64| LoanMsg::UpdateInterestRate(new_val, index))}
+						/>
+					}
 				</div>
 				<div class="col l2 s12">
-					<InputNumber<i64>
-						value={ctx.props().loan.number_of_payments}
-						{index}
-						{input_class}
-						id="loan_number_of_payments"
-						label="Number of Payments"
-						request={link.callback(move |new_val: i64| LoanMsg::UpdateNumberOfPayments(new_val, index))}
-					/>
+					if solve_for == LoanTerm::NumberOfPayments {
+						<span class="hide-on-med-and-up">{ "Number of Payments: " }</span>
+						{ solved_value(loan.number_of_payments.to_string()) }
+					} else {
+						<InputNumber<i64>
+							value={loan.number_of_payments}
+							{index}
+							{input_class}
+							id="loan_number_of_payments"
+							label="Number of Payments"
+							request={link.callback(move |new_val: i64| LoanMsg::UpdateNumberOfPayments(new_val, index))}
+						/>
+					}
 				</div>
 				<div class="col l2 s12">
-					<span class="hide-on-med-and-up">{ "Monthly Payment: " }</span>
-					{ calculated_payment_amount }
+					if solve_for == LoanTerm::PaymentAmount {
+						<span class="hide-on-med-and-up">{ "Monthly Payment: " }</span>
+						{ solved_value(loan.payment_amount.to_string()) }
+					} else {
+						<InputNumber<f64>
+							value={loan.payment_amount.to_f64()}
+							step=".01"
+							{index}
+							{input_class}
+							id="loan_payment_amount"
+							label="Monthly Payment"
+							request={link.callback(move |new_val: f64| LoanMsg::UpdatePaymentAmount(new_val, index))}
+						/>
+					}
 				</div>
-				<div class="col l2 s12">
+				<div class="col l1 s12">
+					<select
+						class="browser-default"
+						onchange={link.callback(move |event: Event| {
+							let select: HtmlSelectElement = event.target_unchecked_into();
+							LoanMsg::UpdateSolveFor(select.value(), index)
+						})}
+					>
+						{ for SOLVE_FOR_CHOICES.iter().map(|(term, label)| html! {
+							<option value={term.to_string()} selected={*term == solve_for}>{ *label }</option>
+						}) }
+					</select>
+				</div>
+				<div class="col l1 s12">
 					<span class="btn" onclick={link.callback(move |_| LoanMsg::Delete(index))}>
 						<i class="small material-icons">{ "delete_forever" }</i>
 					</span>
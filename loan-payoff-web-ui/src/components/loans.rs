@@ -1,7 +1,9 @@
 use super::event_bus::{EventBus, Request};
 use crate::components::LoanRow;
-use loan_payoff::{self, Loan};
-use web_sys::{HtmlInputElement, InputEvent};
+use loan_payoff::{
+	self, FixedOrder, Loan, LoanTerm, Money, PayoffStrategy, PromotionDeadlines, Schedule, Split,
+};
+use web_sys::{Event, HtmlInputElement, HtmlSelectElement, InputEvent};
 use yew::prelude::*;
 use yew::virtual_dom::VChild;
 use yew_agent::{Bridge, Bridged, Dispatched, Dispatcher};
@@ -11,18 +13,80 @@ pub enum LoansMsg {
 	Calculate,
 	UpdateExtraAmount(String),
 	UpdateLoans(Vec<LoanViewModel>),
+	UpdateStrategy(String),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum StrategyChoice {
+	Optimal,
+	DebtSnowball,
+	DebtAvalanche,
+	HighestPaymentFirst,
+	ListedOrder,
+}
+
+const STRATEGY_CHOICES: [StrategyChoice; 5] = [
+	StrategyChoice::Optimal,
+	StrategyChoice::DebtSnowball,
+	StrategyChoice::DebtAvalanche,
+	StrategyChoice::HighestPaymentFirst,
+	StrategyChoice::ListedOrder,
+];
+
+impl StrategyChoice {
+	fn id(&self) -> &'static str {
+		match self {
+			StrategyChoice::Optimal => "optimal",
+			StrategyChoice::DebtSnowball => "snowball",
+			StrategyChoice::DebtAvalanche => "avalanche",
+			StrategyChoice::HighestPaymentFirst => "highest-payment",
+			StrategyChoice::ListedOrder => "listed",
+		}
+	}
+
+	fn label(&self) -> &'static str {
+		match self {
+			StrategyChoice::Optimal => "Best of all orderings",
+			StrategyChoice::DebtSnowball => "Debt snowball (smallest balance first)",
+			StrategyChoice::DebtAvalanche => "Debt avalanche (highest rate first)",
+			StrategyChoice::HighestPaymentFirst => "Highest payment first",
+			StrategyChoice::ListedOrder => "In the order listed",
+		}
+	}
+
+	// None for Optimal, which searches the orderings instead of following a strategy
+	fn strategy(&self, loan_count: usize) -> Option<Box<dyn PayoffStrategy>> {
+		match self {
+			StrategyChoice::Optimal => None,
+			StrategyChoice::DebtSnowball => Some(Box::new(loan_payoff::DebtSnowball)),
+			StrategyChoice::DebtAvalanche => Some(Box::new(loan_payoff::DebtAvalanche)),
+			StrategyChoice::HighestPaymentFirst => Some(Box::new(loan_payoff::HighestPaymentFirst)),
+			StrategyChoice::ListedOrder => Some(Box::new(FixedOrder((0..loan_count).collect()))),
+		}
+	}
 }
 
 #[derive(Clone)]
 pub struct LoanViewModel {
 	pub key: i64, // Unique id to use as a key
 	pub loan: Loan,
+	pub solve_for: LoanTerm,
+	// Whether solve_for could be worked out from the other terms
+	pub solved: bool,
+}
+
+// Schedule for the last calculation, with the loan names as they were at the time
+struct ScheduleViewModel {
+	loan_names: Vec<String>,
+	schedule: Schedule,
 }
 
 pub struct Loans {
 	loans: Vec<LoanViewModel>,
-	extra_amount: f64,
+	extra_amount: Money,
+	strategy: StrategyChoice,
 	optimal_payoff_display: String,
+	schedule: Option<ScheduleViewModel>,
 	event_bus: Dispatcher<EventBus>,
 	_producer: Box<dyn Bridge<EventBus>>,
 	show_validation_errors: bool,
@@ -36,8 +100,10 @@ impl Component for Loans {
 		let mut me = Self {
 			loans: Vec::new(),
 			// TODO: this should be set via ui as well
-			extra_amount: 100.0,
+			extra_amount: Money::from_f64(100.0),
+			strategy: StrategyChoice::Optimal,
 			optimal_payoff_display: "".to_owned(),
+			schedule: None,
 			event_bus: EventBus::dispatcher(),
 			_producer: EventBus::bridge(ctx.link().callback(LoansMsg::UpdateLoans)),
 			show_validation_errors: false,
@@ -51,7 +117,7 @@ impl Component for Loans {
 		match msg {
 			LoansMsg::Calculate => {
 				for loan in self.loans.iter() {
-					if loan.loan.payment_amount.is_nan() || loan.loan.payment_amount.is_infinite() {
+					if !loan.solved {
 						self.show_validation_errors = true;
 						return true;
 					}
@@ -66,20 +132,24 @@ impl Component for Loans {
 				for loan in self.loans.iter() {
 					loans.push(&loan.loan);
 				}
-				match loan_payoff::pay_loans_all_orderings(&loans, self.extra_amount) {
-					Ok(optimal_payoff) => {
+				match self.calculate(&loans) {
+					Ok((stra, strategy)) => {
 						self.show_validation_errors = false;
-						let stra = format!(
-							"Best ordering = {}, with savings ${}, is debt snowball {}, savings over debt snowball ${}",
-							optimal_payoff.ordering.iter().map(|&i| loans[i].name.as_ref()).collect::<Vec<_>>().join(" -> "),
-							optimal_payoff.savings,
-							optimal_payoff.is_debt_snowball,
-							optimal_payoff.savings_over_debt_snowball
-						);
 						self.optimal_payoff_display = stra;
+						self.schedule = loan_payoff::amortization_schedule(
+							&loans,
+							self.extra_amount,
+							strategy.as_ref(),
+						)
+						.ok()
+						.map(|schedule| ScheduleViewModel {
+							loan_names: loans.iter().map(|l| l.name.clone()).collect(),
+							schedule,
+						});
 					}
 					Err(e) => {
-						self.optimal_payoff_display = format!("Err {:?}", e);
+						self.optimal_payoff_display = e.to_string();
+						self.schedule = None;
 					}
 				}
 
@@ -93,13 +163,19 @@ impl Component for Loans {
 			}
 			LoansMsg::UpdateExtraAmount(content) => {
 				// TODO: if empty this throws, need to allow empty but disallow non-numerical
-				self.extra_amount = content.parse::<f64>().unwrap();
+				self.extra_amount = content.parse::<Money>().unwrap();
 				true
 			}
 			LoansMsg::UpdateLoans(loans) => {
 				self.loans = loans;
 				true
 			}
+			LoansMsg::UpdateStrategy(id) => {
+				if let Some(&strategy) = STRATEGY_CHOICES.iter().find(|s| s.id() == id) {
+					self.strategy = strategy;
+				}
+				true
+			}
 		}
 	}
 
@@ -111,7 +187,7 @@ impl Component for Loans {
 			.enumerate()
 			.map(|(index, item)| {
 				html_nested! {
-					<LoanRow key={item.key} loan={item.loan.clone()} index={index} show_validation_errors={self.show_validation_errors} />
+					<LoanRow key={item.key} loan={item.loan.clone()} solve_for={item.solve_for} solved={item.solved} index={index} show_validation_errors={self.show_validation_errors} />
 				}
 			})
 			.collect();
@@ -130,13 +206,29 @@ impl Component for Loans {
 					/>
 					<label for="extra_payment" class="active">{ "Extra Payment" }</label>
 				</div>
+				<div class="input-field">
+					<select
+						id="strategy"
+						class="browser-default"
+						onchange={link.callback(|event: Event| {
+							let select: HtmlSelectElement = event.target_unchecked_into();
+							LoansMsg::UpdateStrategy(select.value())
+						})}
+					>
+						{ for STRATEGY_CHOICES.iter().map(|choice| html! {
+							<option value={choice.id()} selected={*choice == self.strategy}>{ choice.label() }</option>
+						}) }
+					</select>
+					<label for="strategy" class="active">{ "Strategy" }</label>
+				</div>
 				<div class="row hide-on-small-only">
 					<div class="col l2">{ "Name" }</div>
 					<div class="col l2">{ "Loan Amount" }</div>
 					<div class="col l2">{ "Interest Rate" }</div>
 					<div class="col l2">{ "Number of Payments" }</div>
 					<div class="col l2">{ "Monthly Payment" }</div>
-					<div class="col l2"></div>
+					<div class="col l1">{ "Solve For" }</div>
+					<div class="col l1"></div>
 				</div>
 				{ items }
 				<button onclick={link.callback(|_| LoansMsg::Calculate)} class="btn space-right">
@@ -150,7 +242,102 @@ impl Component for Loans {
 						{ self.optimal_payoff_display.clone() }
 					</div>
 				}
+				if let Some(schedule) = &self.schedule {
+					{ view_schedule(schedule) }
+				}
 			</div>
 		}
 	}
 }
+
+impl Loans {
+	// Returns the text to show and the strategy that reproduces the result, for building the schedule
+	fn calculate(
+		&self,
+		loans: &[&Loan],
+	) -> Result<(String, Box<dyn PayoffStrategy>), loan_payoff::Error> {
+		let ordering_names = |ordering: &[usize]| {
+			ordering
+				.iter()
+				.map(|&i| loans[i].name.as_ref())
+				.collect::<Vec<_>>()
+				.join(" -> ")
+		};
+
+		match self.strategy.strategy(loans.len()) {
+			None => {
+				let optimal_payoff = loan_payoff::optimal_payoff(loans, self.extra_amount)?;
+				let stra = format!(
+					"Best ordering = {}, with savings ${}, is debt snowball {}, savings over debt snowball ${}",
+					ordering_names(&optimal_payoff.best.ordering),
+					optimal_payoff.best.savings_total,
+					optimal_payoff.best.is_debt_snowball,
+					optimal_payoff.savings_over_debt_snowball
+				);
+				// the schedule has to follow the best payoff exactly, split and promotion deadlines included
+				let mut strategy: Box<dyn PayoffStrategy> = Box::new(Split {
+					allocation: optimal_payoff.best.allocation,
+					then: Box::new(FixedOrder(optimal_payoff.best.ordering)),
+				});
+				if optimal_payoff.best.promotion_deadlines {
+					strategy = Box::new(PromotionDeadlines(strategy));
+				}
+				Ok((stra, strategy))
+			}
+			Some(strategy) => {
+				let result = loan_payoff::pay_loans_with_strategy(
+					loans,
+					self.extra_amount,
+					strategy.as_ref(),
+				)?;
+				let stra = format!(
+					"{} starts with ordering = {}, with savings ${}, paid off after {} months",
+					self.strategy.label(),
+					ordering_names(&result.ordering),
+					result.savings_total,
+					result.total_periods
+				);
+				Ok((stra, strategy))
+			}
+		}
+	}
+}
+
+fn view_schedule(view_model: &ScheduleViewModel) -> Html {
+	let rows = view_model.schedule.periods.iter().flat_map(|period| {
+		period.entries.iter().map(move |entry| {
+			html! {
+				<tr>
+					<td>{ period.period }</td>
+					<td>{ view_model.loan_names[entry.loan].clone() }</td>
+					<td>{ entry.starting_balance.to_string() }</td>
+					<td>{ entry.interest.to_string() }</td>
+					<td>{ entry.scheduled_payment.to_string() }</td>
+					<td>{ entry.extra_payment.to_string() }</td>
+					<td>{ entry.ending_balance.to_string() }</td>
+					<td>{ entry.cumulative_interest.to_string() }</td>
+				</tr>
+			}
+		})
+	});
+
+	html! {
+		<table class="striped">
+			<thead>
+				<tr>
+					<th>{ "Period" }</th>
+					<th>{ "Loan" }</th>
+					<th>{ "Starting Balance" }</th>
+					<th>{ "Interest" }</th>
+					<th>{ "Payment" }</th>
+					<th>{ "Extra" }</th>
+					<th>{ "Ending Balance" }</th>
+					<th>{ "Total Interest" }</th>
+				</tr>
+			</thead>
+			<tbody>
+				{ for rows }
+			</tbody>
+		</table>
+	}
+}
@@ -0,0 +1,180 @@
+use crate::Money;
+
+/// Shares of the extra amount set aside for particular loans each period
+///
+/// The rules are applied in order, each taking its share out of what the earlier ones left. Whatever is left
+/// after the last rule goes to the loans in the strategy's ordering as usual. A rule for a loan that is already
+/// paid off is skipped, so its share goes back to the ordering too.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Allocation {
+	pub rules: Vec<AllocationRule>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AllocationRule {
+	/// Index of the loan, the same way the loans are passed in
+	pub loan: usize,
+	pub share: Share,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Share {
+	/// Fraction of the period's extra amount, 0.25 for 25%
+	Percentage(f64),
+	/// The same amount every period, or whatever is left of the extra if that is less
+	Fixed(Money),
+}
+
+impl Allocation {
+	pub fn new(rules: Vec<AllocationRule>) -> Allocation {
+		Allocation { rules }
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.rules.is_empty()
+	}
+
+	/// Every way of giving each loan a multiple of `step` (0.25 for 25%) of the extra amount, up to all of it
+	///
+	/// The first one is the empty allocation, where the whole extra follows the ordering.
+	pub fn percentage_splits(loan_count: usize, step: f64) -> Vec<Allocation> {
+		let parts = (1.0 / step).round().max(1.0) as u32;
+		let mut splits = Vec::new();
+		let mut counts = vec![0; loan_count];
+		add_splits(&mut splits, &mut counts, 0, parts, parts);
+		splits
+	}
+
+	pub(crate) fn validate(&self, loan_count: usize) -> Result<(), String> {
+		for rule in self.rules.iter() {
+			if rule.loan >= loan_count {
+				return Err(format!(
+					"allocation rule for loan {}, but there are only {} loans",
+					rule.loan, loan_count
+				));
+			}
+			match rule.share {
+				Share::Percentage(p) if !(0.0..=1.0).contains(&p) => {
+					return Err(format!(
+						"allocation for loan {} is {}, expected a fraction from 0 to 1",
+						rule.loan, p
+					))
+				}
+				Share::Fixed(amount) if amount.is_negative() => {
+					return Err(format!(
+						"allocation for loan {} is ${}, expected an amount of at least $0.00",
+						rule.loan, amount
+					))
+				}
+				_ => {}
+			}
+		}
+		Ok(())
+	}
+
+	/// Splits the extra amount into a share for each loan and the rest, which follows the ordering
+	///
+	/// The shares are empty when there are no rules, so the simulation doesn't allocate for the common case.
+	pub(crate) fn split(&self, extra: Money, remaining_amounts: &[Money]) -> (Vec<Money>, Money) {
+		if self.rules.is_empty() || !extra.is_positive() {
+			return (Vec::new(), extra);
+		}
+		let mut shares = vec![Money::ZERO; remaining_amounts.len()];
+		let mut rest = extra;
+		for rule in self.rules.iter() {
+			if !remaining_amounts[rule.loan].is_positive() {
+				continue;
+			}
+			let share = match rule.share {
+				Share::Percentage(p) => extra.mul_rate(p),
+				Share::Fixed(amount) => amount,
+			}
+			.min(rest);
+			shares[rule.loan] += share;
+			rest -= share;
+		}
+		(shares, rest)
+	}
+}
+
+fn add_splits(
+	splits: &mut Vec<Allocation>,
+	counts: &mut [u32],
+	loan: usize,
+	left: u32,
+	parts: u32,
+) {
+	if loan == counts.len() {
+		let rules = counts
+			.iter()
+			.enumerate()
+			.filter(|(_, &count)| count > 0)
+			.map(|(loan, &count)| AllocationRule {
+				loan,
+				share: Share::Percentage(count as f64 / parts as f64),
+			})
+			.collect();
+		splits.push(Allocation::new(rules));
+		return;
+	}
+	for count in 0..=left {
+		counts[loan] = count;
+		add_splits(splits, counts, loan + 1, left - count, parts);
+	}
+	counts[loan] = 0;
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use test_case::test_case;
+
+	fn rule(loan: usize, share: Share) -> AllocationRule {
+		AllocationRule { loan, share }
+	}
+
+	#[test_case(&[10000, 10000, 10000] => (vec![2500, 5000, 0], 2500))]
+	#[test_case(&[0, 10000, 10000] => (vec![0, 5000, 0], 5000))]
+	#[test_case(&[10000, 0, 10000] => (vec![2500, 0, 0], 7500))]
+	fn split(remaining: &[i64]) -> (Vec<i64>, i64) {
+		let allocation = Allocation::new(vec![
+			rule(0, Share::Percentage(0.25)),
+			rule(1, Share::Fixed(Money::from_cents(5000))),
+		]);
+		let remaining: Vec<Money> = remaining.iter().map(|&c| Money::from_cents(c)).collect();
+		let (shares, rest) = allocation.split(Money::from_cents(10000), &remaining);
+		(shares.iter().map(|s| s.cents()).collect(), rest.cents())
+	}
+
+	#[test]
+	fn split_never_gives_out_more_than_the_extra() {
+		let allocation = Allocation::new(vec![
+			rule(0, Share::Fixed(Money::from_cents(8000))),
+			rule(1, Share::Percentage(0.5)),
+		]);
+		let remaining = [Money::from_cents(10000), Money::from_cents(10000)];
+		let (shares, rest) = allocation.split(Money::from_cents(10000), &remaining);
+		assert_eq!(
+			shares,
+			vec![Money::from_cents(8000), Money::from_cents(2000)]
+		);
+		assert_eq!(rest, Money::ZERO);
+	}
+
+	#[test_case(1, 0.25 => 5)]
+	#[test_case(2, 0.5 => 6)]
+	#[test_case(3, 0.25 => 35)]
+	fn percentage_splits(loan_count: usize, step: f64) -> usize {
+		let splits = Allocation::percentage_splits(loan_count, step);
+		assert!(splits[0].is_empty());
+		splits.len()
+	}
+
+	#[test_case(rule(3, Share::Percentage(0.5)) => false)]
+	#[test_case(rule(0, Share::Percentage(1.5)) => false)]
+	#[test_case(rule(0, Share::Fixed(Money::from_cents(-1))) => false)]
+	#[test_case(rule(2, Share::Fixed(Money::from_cents(100))) => true)]
+	fn validate(rule: AllocationRule) -> bool {
+		Allocation::new(vec![rule]).validate(3).is_ok()
+	}
+}
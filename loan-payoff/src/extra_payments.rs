@@ -0,0 +1,281 @@
+use crate::Money;
+use serde::Deserialize;
+use std::fmt;
+
+/// How much extra goes toward the loans each month, on top of their regular payments
+///
+/// Anything paid off loans free up is added on top of this by the simulation. Periods here are always months,
+/// when some loans are paid more often the simulation spreads each month's amount over the periods in it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtraPayments {
+	budget: Budget,
+	lump_sums: Vec<LumpSum>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Budget {
+	Constant(Money),
+	Ranges(Vec<ExtraPaymentRange>),
+	Growing {
+		starting_amount: Money,
+		annual_growth: f64,
+	},
+}
+
+/// An extra amount paid in every period from `first_period` through `last_period`
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct ExtraPaymentRange {
+	/// 1-based month, the same numbering as `SchedulePeriod::period` when every loan is paid monthly
+	pub first_period: i64,
+	/// Inclusive, `None` keeps paying until the loans are paid off
+	pub last_period: Option<i64>,
+	pub amount: Money,
+}
+
+/// A one-off amount like a tax refund or bonus
+///
+/// It is paid at the end of the first period of its month, after the regular and extra payments, to the first loan
+/// in the ordering that still has a balance. Whatever that loan doesn't need moves on to the next one.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct LumpSum {
+	/// 1-based month of the (first) payment
+	pub period: i64,
+	pub amount: Money,
+	/// Paid again every 12 periods after the first
+	#[serde(default)]
+	pub yearly: bool,
+}
+
+impl LumpSum {
+	pub fn once(period: i64, amount: Money) -> LumpSum {
+		LumpSum {
+			period,
+			amount,
+			yearly: false,
+		}
+	}
+
+	pub fn yearly(first_period: i64, amount: Money) -> LumpSum {
+		LumpSum {
+			period: first_period,
+			amount,
+			yearly: true,
+		}
+	}
+
+	fn is_paid_in(&self, period: i64) -> bool {
+		if self.yearly {
+			period >= self.period && (period - self.period) % 12 == 0
+		} else {
+			period == self.period
+		}
+	}
+}
+
+impl ExtraPaymentRange {
+	fn contains(&self, period: i64) -> bool {
+		period >= self.first_period && self.last_period.is_none_or(|last| period <= last)
+	}
+}
+
+impl ExtraPayments {
+	/// The same amount every period
+	pub fn constant(amount: Money) -> ExtraPayments {
+		ExtraPayments {
+			budget: Budget::Constant(amount),
+			lump_sums: Vec::new(),
+		}
+	}
+
+	/// Amounts by range of periods, ranges that overlap add up and periods outside every range get nothing
+	pub fn ranges(ranges: Vec<ExtraPaymentRange>) -> ExtraPayments {
+		ExtraPayments {
+			budget: Budget::Ranges(ranges),
+			lump_sums: Vec::new(),
+		}
+	}
+
+	/// Starts at `starting_amount` and grows by `annual_growth` (0.03 for 3%) every 12 periods
+	pub fn growing(starting_amount: Money, annual_growth: f64) -> ExtraPayments {
+		ExtraPayments {
+			budget: Budget::Growing {
+				starting_amount,
+				annual_growth,
+			},
+			lump_sums: Vec::new(),
+		}
+	}
+
+	/// Adds lump sums on top of the regular extra payment
+	pub fn with_lump_sums(mut self, lump_sums: impl IntoIterator<Item = LumpSum>) -> ExtraPayments {
+		self.lump_sums.extend(lump_sums);
+		self
+	}
+
+	/// Total of the lump sums paid in a 1-based period
+	pub fn lump_sum_for_period(&self, period: i64) -> Money {
+		self.lump_sums
+			.iter()
+			.filter(|l| l.is_paid_in(period))
+			.map(|l| l.amount)
+			.sum()
+	}
+
+	/// The regular extra amount for a 1-based period, not counting lump sums
+	pub fn amount_for_period(&self, period: i64) -> Money {
+		match &self.budget {
+			Budget::Constant(amount) => *amount,
+			Budget::Ranges(ranges) => ranges
+				.iter()
+				.filter(|r| r.contains(period))
+				.map(|r| r.amount)
+				.sum(),
+			Budget::Growing {
+				starting_amount,
+				annual_growth,
+			} => {
+				let years = (period - 1).max(0) / 12;
+				starting_amount.mul_rate((1.0 + annual_growth).powi(years as i32))
+			}
+		}
+	}
+}
+
+impl Default for ExtraPayments {
+	fn default() -> ExtraPayments {
+		ExtraPayments::constant(Money::ZERO)
+	}
+}
+
+impl From<Money> for ExtraPayments {
+	fn from(amount: Money) -> ExtraPayments {
+		ExtraPayments::constant(amount)
+	}
+}
+
+impl fmt::Display for ExtraPayments {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match &self.budget {
+			Budget::Constant(amount) => write!(f, "${}", amount)?,
+			Budget::Ranges(ranges) => {
+				for (i, range) in ranges.iter().enumerate() {
+					if i > 0 {
+						write!(f, ", ")?;
+					}
+					match range.last_period {
+						Some(last) => write!(
+							f,
+							"${} in periods {} to {}",
+							range.amount, range.first_period, last
+						)?,
+						None => write!(f, "${} from period {}", range.amount, range.first_period)?,
+					}
+				}
+			}
+			Budget::Growing {
+				starting_amount,
+				annual_growth,
+			} => write!(
+				f,
+				"${} growing {}% a year",
+				starting_amount,
+				annual_growth * 100.0
+			)?,
+		}
+		for lump_sum in self.lump_sums.iter() {
+			if lump_sum.yearly {
+				write!(
+					f,
+					", plus ${} every 12 periods from period {}",
+					lump_sum.amount, lump_sum.period
+				)?;
+			} else {
+				write!(
+					f,
+					", plus ${} in period {}",
+					lump_sum.amount, lump_sum.period
+				)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use test_case::test_case;
+
+	fn range(first_period: i64, last_period: Option<i64>, amount: i64) -> ExtraPaymentRange {
+		ExtraPaymentRange {
+			first_period,
+			last_period,
+			amount: Money::from_cents(amount),
+		}
+	}
+
+	#[test_case(1 => 10000)]
+	#[test_case(12 => 10000)]
+	#[test_case(13 => 25000)]
+	#[test_case(24 => 25000)]
+	#[test_case(25 => 55000)]
+	#[test_case(400 => 55000)]
+	fn ranges(period: i64) -> i64 {
+		let extra = ExtraPayments::ranges(vec![
+			range(1, Some(12), 10000),
+			range(13, None, 25000),
+			range(25, None, 30000),
+		]);
+		extra.amount_for_period(period).cents()
+	}
+
+	#[test_case(1 => 10000)]
+	#[test_case(12 => 10000)]
+	#[test_case(13 => 10300)]
+	#[test_case(25 => 10609)]
+	fn growing(period: i64) -> i64 {
+		let extra = ExtraPayments::growing(Money::from_cents(10000), 0.03);
+		extra.amount_for_period(period).cents()
+	}
+
+	#[test_case(1 => 0)]
+	#[test_case(3 => 50000)]
+	#[test_case(12 => 0)]
+	#[test_case(14 => 0)]
+	#[test_case(15 => 200000)]
+	#[test_case(27 => 200000)]
+	fn lump_sums(period: i64) -> i64 {
+		let extra = ExtraPayments::constant(Money::from_cents(10000)).with_lump_sums([
+			LumpSum::once(3, Money::from_cents(50000)),
+			LumpSum::yearly(15, Money::from_cents(200000)),
+		]);
+		assert_eq!(extra.amount_for_period(period), Money::from_cents(10000));
+		extra.lump_sum_for_period(period).cents()
+	}
+
+	#[test]
+	fn display() {
+		assert_eq!(
+			ExtraPayments::constant(Money::from_cents(10000)).to_string(),
+			"$100.00"
+		);
+		assert_eq!(
+			ExtraPayments::ranges(vec![range(1, Some(12), 10000), range(13, None, 25000)])
+				.to_string(),
+			"$100.00 in periods 1 to 12, $250.00 from period 13"
+		);
+		assert_eq!(
+			ExtraPayments::growing(Money::from_cents(10000), 0.03).to_string(),
+			"$100.00 growing 3% a year"
+		);
+		assert_eq!(
+			ExtraPayments::constant(Money::from_cents(10000))
+				.with_lump_sums([
+					LumpSum::once(3, Money::from_cents(50000)),
+					LumpSum::yearly(15, Money::from_cents(200000)),
+				])
+				.to_string(),
+			"$100.00, plus $500.00 in period 3, plus $2000.00 every 12 periods from period 15"
+		);
+	}
+}
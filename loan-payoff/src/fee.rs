@@ -0,0 +1,53 @@
+use crate::Money;
+
+/// A fee charged on a loan for as long as it has a balance
+///
+/// Fees are charged at the start of the month on top of the payments, so they count as a cost of the loan without
+/// adding to its balance. Paying the loan off sooner stops them, which counts as savings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fee {
+	pub amount: Money,
+	pub frequency: FeeFrequency,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FeeFrequency {
+	/// Every month, like a service fee
+	Monthly,
+	/// Once a year, in the given 1-based month of the loan and every 12 months after, like a card's annual fee
+	Annual { month: i64 },
+	/// In each of the given 1-based months, like late fees for payments known to be late
+	Months(Vec<i64>),
+}
+
+impl Fee {
+	/// Whether the fee is charged in a 1-based month
+	pub fn is_charged(&self, month: i64) -> bool {
+		match &self.frequency {
+			FeeFrequency::Monthly => true,
+			FeeFrequency::Annual { month: first } => month >= *first && (month - first) % 12 == 0,
+			FeeFrequency::Months(months) => months.contains(&month),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use test_case::test_case;
+
+	#[test_case(FeeFrequency::Monthly, 7 => true)]
+	#[test_case(FeeFrequency::Annual { month: 3 }, 1 => false)]
+	#[test_case(FeeFrequency::Annual { month: 3 }, 3 => true)]
+	#[test_case(FeeFrequency::Annual { month: 3 }, 15 => true)]
+	#[test_case(FeeFrequency::Annual { month: 3 }, 16 => false)]
+	#[test_case(FeeFrequency::Months(vec![2, 9]), 9 => true)]
+	#[test_case(FeeFrequency::Months(vec![2, 9]), 10 => false)]
+	fn is_charged(frequency: FeeFrequency, month: i64) -> bool {
+		let fee = Fee {
+			amount: Money::from_cents(2500),
+			frequency,
+		};
+		fee.is_charged(month)
+	}
+}
@@ -0,0 +1,275 @@
+//! Time value of money functions that work the way the spreadsheet functions of the same name do
+//!
+//! Money paid out is negative and money received is positive, so borrowing 10,000 is a `pv` of 10000.0 and the
+//! payments on it come back negative. `rate` is the rate per period and `nper` the number of periods. Where a
+//! spreadsheet would show an error these return `None` instead of NaN or infinity, a zero rate is fine.
+
+/// Whether payments are made at the end of each period, as loan payments are, or at the start
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PaymentTiming {
+	#[default]
+	End,
+	Begin,
+}
+
+impl PaymentTiming {
+	/// Periods of interest a payment earns on top of the end-of-period payment, 0 or 1
+	fn offset(self) -> f64 {
+		match self {
+			PaymentTiming::End => 0.0,
+			PaymentTiming::Begin => 1.0,
+		}
+	}
+}
+
+/// Payment each period, PMT
+pub fn pmt(rate: f64, nper: f64, pv: f64, fv: f64, timing: PaymentTiming) -> Option<f64> {
+	if nper == 0.0 {
+		return None;
+	}
+	if rate == 0.0 {
+		return finite(-(pv + fv) / nper);
+	}
+	let growth = f64::powf(1.0 + rate, nper);
+	finite(-rate * (pv * growth + fv) / ((1.0 + rate * timing.offset()) * (growth - 1.0)))
+}
+
+/// Present value, PV
+pub fn pv(rate: f64, nper: f64, pmt: f64, fv: f64, timing: PaymentTiming) -> Option<f64> {
+	if rate == 0.0 {
+		return finite(-(fv + pmt * nper));
+	}
+	let growth = f64::powf(1.0 + rate, nper);
+	finite(-(fv + pmt * annuity_factor(rate, nper, timing)) / growth)
+}
+
+/// Future value, FV
+pub fn fv(rate: f64, nper: f64, pmt: f64, pv: f64, timing: PaymentTiming) -> Option<f64> {
+	if rate == 0.0 {
+		return finite(-(pv + pmt * nper));
+	}
+	let growth = f64::powf(1.0 + rate, nper);
+	finite(-(pv * growth + pmt * annuity_factor(rate, nper, timing)))
+}
+
+/// Number of periods, NPER, which usually isn't a whole number
+pub fn nper(rate: f64, pmt: f64, pv: f64, fv: f64, timing: PaymentTiming) -> Option<f64> {
+	if rate == 0.0 {
+		if pmt == 0.0 {
+			return None;
+		}
+		return finite(-(pv + fv) / pmt);
+	}
+	let z = pmt * (1.0 + rate * timing.offset()) / rate;
+	let ratio = (z - fv) / (pv + z);
+	if ratio <= 0.0 {
+		return None;
+	}
+	finite(ratio.ln() / (1.0 + rate).ln())
+}
+
+/// Rate per period, RATE, starting from `guess`, 0.1 in a spreadsheet
+///
+/// Newton's method from the guess, falling back to bisection when it doesn't settle. `None` when no rate above
+/// -100% gives the cash flows.
+pub fn rate(
+	nper: f64,
+	pmt: f64,
+	pv: f64,
+	fv: f64,
+	timing: PaymentTiming,
+	guess: f64,
+) -> Option<f64> {
+	const TOLERANCE: f64 = 1e-10;
+	if nper <= 0.0 {
+		return None;
+	}
+	// what is left over at the end, zero at the rate being looked for
+	let balance = |rate: f64| match self::fv(rate, nper, pmt, pv, timing) {
+		Some(fv_at_rate) => fv_at_rate - fv,
+		None => f64::NAN,
+	};
+	let tolerance = TOLERANCE * (pv.abs() + fv.abs() + (pmt * nper).abs()).max(1.0);
+
+	let mut rate = guess;
+	for _ in 0..100 {
+		let y = balance(rate);
+		if y.abs() < tolerance {
+			return Some(rate);
+		}
+		let h = 1e-7 * rate.abs().max(1e-3);
+		let slope = (balance(rate + h) - balance(rate - h)) / (2.0 * h);
+		let next = rate - y / slope;
+		if !next.is_finite() || next <= -1.0 {
+			break;
+		}
+		rate = next;
+	}
+
+	// the balance changes sign at most once for a loan's cash flows, look for it between -99% and a rate big
+	// enough to flip it
+	let mut low = -0.99;
+	let mut high = 1.0;
+	while balance(low).signum() == balance(high).signum() {
+		high *= 2.0;
+		if high > 1e6 || !balance(high).is_finite() {
+			return None;
+		}
+	}
+	for _ in 0..200 {
+		let middle = (low + high) / 2.0;
+		let y = balance(middle);
+		if y.abs() < tolerance {
+			return Some(middle);
+		}
+		if y.signum() == balance(low).signum() {
+			low = middle;
+		} else {
+			high = middle;
+		}
+	}
+	Some((low + high) / 2.0)
+}
+
+/// Interest part of the payment in the 1-based period `per`, IPMT
+pub fn ipmt(
+	rate: f64,
+	per: f64,
+	nper: f64,
+	pv: f64,
+	fv: f64,
+	timing: PaymentTiming,
+) -> Option<f64> {
+	if per < 1.0 || per > nper {
+		return None;
+	}
+	let payment = pmt(rate, nper, pv, fv, timing)?;
+	if per == 1.0 && timing == PaymentTiming::Begin {
+		// paid before any interest has built up
+		return Some(0.0);
+	}
+	let interest = self::fv(rate, per - 1.0, payment, pv, timing)? * rate;
+	finite(match timing {
+		PaymentTiming::End => interest,
+		PaymentTiming::Begin => interest / (1.0 + rate),
+	})
+}
+
+/// Principal part of the payment in the 1-based period `per`, PPMT
+pub fn ppmt(
+	rate: f64,
+	per: f64,
+	nper: f64,
+	pv: f64,
+	fv: f64,
+	timing: PaymentTiming,
+) -> Option<f64> {
+	Some(pmt(rate, nper, pv, fv, timing)? - ipmt(rate, per, nper, pv, fv, timing)?)
+}
+
+/// What each unit of payment grows to by the end of `nper` periods, at a rate that isn't zero
+fn annuity_factor(rate: f64, nper: f64, timing: PaymentTiming) -> f64 {
+	(1.0 + rate * timing.offset()) * (f64::powf(1.0 + rate, nper) - 1.0) / rate
+}
+
+fn finite(value: f64) -> Option<f64> {
+	Some(value).filter(|v| v.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::round_to_decimals;
+	use test_case::test_case;
+	use PaymentTiming::{Begin, End};
+
+	// Expected values are what a spreadsheet gives for the same arguments
+
+	#[test_case(0.08 / 12.0, 10.0, 10000.0, 0.0, End => Some(-1037.03))]
+	#[test_case(0.08 / 12.0, 10.0, 10000.0, 0.0, Begin => Some(-1030.16))]
+	#[test_case(0.06 / 12.0, 18.0 * 12.0, 0.0, 50000.0, End => Some(-129.08))]
+	#[test_case(0.0, 24.0, 1200.0, 0.0, End => Some(-50.0))]
+	#[test_case(0.0, 24.0, 1200.0, 0.0, Begin => Some(-50.0))]
+	#[test_case(0.05, 0.0, 1000.0, 0.0, End => None)]
+	fn pmt(rate: f64, nper: f64, pv: f64, fv: f64, timing: PaymentTiming) -> Option<f64> {
+		super::pmt(rate, nper, pv, fv, timing).map(|v| round_to_decimals(v, 2))
+	}
+
+	#[test_case(0.08 / 12.0, 12.0 * 20.0, 500.0, 0.0, End => Some(-59777.15))]
+	#[test_case(0.08 / 12.0, 12.0 * 20.0, 500.0, 0.0, Begin => Some(-60175.66))]
+	#[test_case(0.0, 10.0, -100.0, -500.0, End => Some(1500.0))]
+	fn pv(rate: f64, nper: f64, pmt: f64, fv: f64, timing: PaymentTiming) -> Option<f64> {
+		super::pv(rate, nper, pmt, fv, timing).map(|v| round_to_decimals(v, 2))
+	}
+
+	#[test_case(0.06 / 12.0, 10.0, -200.0, -500.0, Begin => Some(2581.40))]
+	#[test_case(0.12 / 12.0, 12.0, -1000.0, 0.0, End => Some(12682.50))]
+	#[test_case(0.11 / 12.0, 35.0, -2000.0, 0.0, Begin => Some(82846.25))]
+	#[test_case(0.0, 12.0, -100.0, -1000.0, End => Some(2200.0))]
+	fn fv(rate: f64, nper: f64, pmt: f64, pv: f64, timing: PaymentTiming) -> Option<f64> {
+		super::fv(rate, nper, pmt, pv, timing).map(|v| round_to_decimals(v, 2))
+	}
+
+	#[test_case(0.12 / 12.0, -100.0, -1000.0, 10000.0, Begin => Some(59.6738657))]
+	#[test_case(0.12 / 12.0, -100.0, -1000.0, 10000.0, End => Some(60.0821229))]
+	#[test_case(0.12 / 12.0, -100.0, -1000.0, 0.0, End => Some(-9.578594))]
+	#[test_case(0.0, -100.0, 1000.0, 0.0, End => Some(10.0))]
+	#[test_case(0.12 / 12.0, -5.0, 1000.0, 0.0, End => None)] // the payment doesn't cover the interest
+	#[test_case(0.0, 0.0, 1000.0, 0.0, End => None)]
+	fn nper(rate: f64, pmt: f64, pv: f64, fv: f64, timing: PaymentTiming) -> Option<f64> {
+		super::nper(rate, pmt, pv, fv, timing).map(|v| round_to_decimals(v, 7))
+	}
+
+	#[test_case(4.0 * 12.0, -200.0, 8000.0, 0.0, End, 0.1 => Some(0.0077))]
+	#[test_case(60.0, -188.71, 10000.0, 0.0, End, 0.1 => Some(0.0042))]
+	#[test_case(10.0, -1030.16, 10000.0, 0.0, Begin, 0.1 => Some(0.0067))]
+	#[test_case(24.0, -50.0, 1200.0, 0.0, End, 0.1 => Some(0.0))]
+	#[test_case(12.0, -100.0, 1000.0, 0.0, End, 0.01 => Some(0.0292))]
+	#[test_case(12.0, 100.0, 1000.0, 0.0, End, 0.1 => None)] // receiving money both ways
+	fn rate(
+		nper: f64,
+		pmt: f64,
+		pv: f64,
+		fv: f64,
+		timing: PaymentTiming,
+		guess: f64,
+	) -> Option<f64> {
+		super::rate(nper, pmt, pv, fv, timing, guess).map(|v| round_to_decimals(v, 4))
+	}
+
+	#[test_case(0.10 / 12.0, 1.0, 3.0 * 12.0, 8000.0, End => Some(-66.67))]
+	#[test_case(0.10, 3.0, 3.0, 8000.0, End => Some(-292.45))]
+	#[test_case(0.10, 1.0, 3.0, 8000.0, Begin => Some(0.0))]
+	#[test_case(0.10, 3.0, 3.0, 8000.0, Begin => Some(-265.86))]
+	#[test_case(0.0, 2.0, 3.0, 8000.0, End => Some(0.0))]
+	#[test_case(0.10, 4.0, 3.0, 8000.0, End => None)]
+	fn ipmt(rate: f64, per: f64, nper: f64, pv: f64, timing: PaymentTiming) -> Option<f64> {
+		super::ipmt(rate, per, nper, pv, 0.0, timing).map(|v| round_to_decimals(v, 2))
+	}
+
+	#[test_case(0.10 / 12.0, 1.0, 2.0 * 12.0, 2000.0, End => Some(-75.62))]
+	#[test_case(0.08, 10.0, 10.0, 200000.0, End => Some(-27598.05))]
+	#[test_case(0.0, 2.0, 4.0, 8000.0, End => Some(-2000.0))]
+	fn ppmt(rate: f64, per: f64, nper: f64, pv: f64, timing: PaymentTiming) -> Option<f64> {
+		super::ppmt(rate, per, nper, pv, 0.0, timing).map(|v| round_to_decimals(v, 2))
+	}
+
+	#[test]
+	fn payments_add_up() {
+		let (rate, nper, pv) = (0.065 / 12.0, 30.0, 25000.0);
+		for timing in [End, Begin] {
+			let payment = super::pmt(rate, nper, pv, 0.0, timing).unwrap();
+			let principal: f64 = (1..=30)
+				.map(|per| super::ppmt(rate, per as f64, nper, pv, 0.0, timing).unwrap())
+				.sum();
+			assert!((principal + pv).abs() < 1e-6);
+			assert!(super::nper(rate, payment, pv, 0.0, timing)
+				.is_some_and(|n| (n - nper).abs() < 1e-9));
+			assert!(super::rate(nper, payment, pv, 0.0, timing, 0.1)
+				.is_some_and(|r| (r - rate).abs() < 1e-9));
+			assert!(
+				super::pv(rate, nper, payment, 0.0, timing).is_some_and(|v| (v - pv).abs() < 1e-6)
+			);
+		}
+	}
+}
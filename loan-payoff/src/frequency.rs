@@ -0,0 +1,181 @@
+use crate::Loan;
+use serde::Deserialize;
+use std::fmt;
+
+/// How often interest is added to the balance
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Compounding {
+	Daily,
+	#[default]
+	Monthly,
+	Annual,
+}
+
+/// How often the regular payment is made
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PaymentFrequency {
+	Weekly,
+	Biweekly,
+	SemiMonthly,
+	#[default]
+	Monthly,
+}
+
+impl Compounding {
+	pub fn periods_per_year(self) -> i64 {
+		match self {
+			Compounding::Daily => 365,
+			Compounding::Monthly => 12,
+			Compounding::Annual => 1,
+		}
+	}
+}
+
+impl PaymentFrequency {
+	pub fn periods_per_year(self) -> i64 {
+		match self {
+			PaymentFrequency::Weekly => 52,
+			PaymentFrequency::Biweekly => 26,
+			PaymentFrequency::SemiMonthly => 24,
+			PaymentFrequency::Monthly => 12,
+		}
+	}
+}
+
+impl fmt::Display for Compounding {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Compounding::Daily => write!(f, "daily"),
+			Compounding::Monthly => write!(f, "monthly"),
+			Compounding::Annual => write!(f, "annual"),
+		}
+	}
+}
+
+impl fmt::Display for PaymentFrequency {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			PaymentFrequency::Weekly => write!(f, "weekly"),
+			PaymentFrequency::Biweekly => write!(f, "biweekly"),
+			PaymentFrequency::SemiMonthly => write!(f, "semi-monthly"),
+			PaymentFrequency::Monthly => write!(f, "monthly"),
+		}
+	}
+}
+
+/// The periods every loan is simulated on, fine enough for the most frequent payments
+///
+/// Loans that are all paid monthly keep monthly periods. Otherwise the periods are the fewest per year that every
+/// payment frequency fits into evenly, or weeks when there is none. A year of weeks is 52 of them, so on weekly
+/// periods monthly and semi-monthly payments are spread as evenly as the weeks allow, still 12 or 24 a year.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timeline {
+	pub periods_per_year: i64,
+}
+
+impl Timeline {
+	pub fn for_loans(loans: &[&Loan]) -> Timeline {
+		let frequencies: Vec<i64> = loans
+			.iter()
+			.map(|l| l.payment_frequency.periods_per_year())
+			.collect();
+		let periods_per_year = [12, 24, 26, 52]
+			.into_iter()
+			.find(|&t| frequencies.iter().all(|&p| t % p == 0))
+			.unwrap_or(52);
+		Timeline { periods_per_year }
+	}
+
+	/// Whether a 1-based period holds one of the loan's payments
+	pub fn is_payment_period(self, loan: &Loan, period: i64) -> bool {
+		let per_year = loan.payment_frequency.periods_per_year();
+		period * per_year / self.periods_per_year > (period - 1) * per_year / self.periods_per_year
+	}
+
+	/// Periods needed to make `number_of_payments` of the loan's payments
+	pub fn periods_for_payments(self, loan: &Loan, number_of_payments: i64) -> i64 {
+		let per_year = loan.payment_frequency.periods_per_year();
+		(number_of_payments * self.periods_per_year + per_year - 1) / per_year
+	}
+
+	/// The 1-based month a 1-based period falls in, extra payments are budgeted by month
+	pub fn month(self, period: i64) -> i64 {
+		(period - 1) * 12 / self.periods_per_year + 1
+	}
+
+	/// Whether a 1-based period is the first one of its month
+	pub fn starts_month(self, period: i64) -> bool {
+		period == 1 || self.month(period) != self.month(period - 1)
+	}
+
+	/// Interest rate of the loan for one period of the timeline
+	pub fn rate(self, loan: &Loan) -> f64 {
+		loan.rate_per(self.periods_per_year)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use test_case::test_case;
+
+	fn loan(payment_frequency: PaymentFrequency) -> Loan {
+		Loan {
+			payment_frequency,
+			..Default::default()
+		}
+	}
+
+	#[test_case(&[PaymentFrequency::Monthly] => 12)]
+	#[test_case(&[PaymentFrequency::Monthly, PaymentFrequency::SemiMonthly] => 24)]
+	#[test_case(&[PaymentFrequency::Biweekly] => 26)]
+	#[test_case(&[PaymentFrequency::Biweekly, PaymentFrequency::Weekly] => 52)]
+	#[test_case(&[PaymentFrequency::Monthly, PaymentFrequency::Biweekly] => 52)]
+	#[test_case(&[] => 12)]
+	fn for_loans(frequencies: &[PaymentFrequency]) -> i64 {
+		let loans: Vec<Loan> = frequencies.iter().map(|&f| loan(f)).collect();
+		let loans: Vec<&Loan> = loans.iter().collect();
+		Timeline::for_loans(&loans).periods_per_year
+	}
+
+	#[test_case(PaymentFrequency::Weekly => 52)]
+	#[test_case(PaymentFrequency::Biweekly => 26)]
+	#[test_case(PaymentFrequency::SemiMonthly => 24)]
+	#[test_case(PaymentFrequency::Monthly => 12)]
+	fn payments_per_year_on_weeks(frequency: PaymentFrequency) -> usize {
+		let timeline = Timeline {
+			periods_per_year: 52,
+		};
+		let loan = loan(frequency);
+		(1..=52)
+			.filter(|&p| timeline.is_payment_period(&loan, p))
+			.count()
+	}
+
+	#[test]
+	fn biweekly_payments_every_other_week() {
+		let timeline = Timeline {
+			periods_per_year: 52,
+		};
+		let loan = loan(PaymentFrequency::Biweekly);
+		let periods: Vec<i64> = (1..=8)
+			.filter(|&p| timeline.is_payment_period(&loan, p))
+			.collect();
+		assert_eq!(periods, vec![2, 4, 6, 8]);
+		assert_eq!(timeline.periods_for_payments(&loan, 26), 52);
+	}
+
+	#[test_case(1 => (1, true))]
+	#[test_case(5 => (1, false))]
+	#[test_case(6 => (2, true))]
+	#[test_case(52 => (12, false))]
+	#[test_case(53 => (13, true))]
+	fn month(period: i64) -> (i64, bool) {
+		let timeline = Timeline {
+			periods_per_year: 52,
+		};
+		(timeline.month(period), timeline.starts_month(period))
+	}
+}
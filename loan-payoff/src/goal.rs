@@ -0,0 +1,148 @@
+use crate::{pay_loans_with_strategy, Error, Loan, Money, PayoffResult, PayoffStrategy};
+
+/// Smallest extra amount that pays every loan off by a target period, and the payoff it gives
+#[derive(Clone, Debug, PartialEq)]
+pub struct RequiredExtraPayment {
+	/// Extra paid every period on top of the regular payments
+	pub extra_amount: Money,
+	pub result: PayoffResult,
+}
+
+/// Finds the smallest extra amount, to the cent, that has every loan paid off by `target_period`
+///
+/// `target_period` counts the same periods as `PayoffResult::total_periods`, months unless some loans are paid
+/// more often. The extra is paid every period and follows the strategy. Fails when no extra amount is enough,
+/// for example when a prepayment cap or a deferment keeps a loan open past the target.
+pub fn required_extra_payment(
+	loans: &[&Loan],
+	target_period: i64,
+	strategy: &dyn PayoffStrategy,
+) -> Result<RequiredExtraPayment, Error> {
+	required_extra_payment_with(loans, target_period, |extra_amount| {
+		pay_loans_with_strategy(loans, extra_amount, strategy)
+	})
+}
+
+/// Same as `required_extra_payment`, with `pay` working out the payoff for a given extra amount
+///
+/// That allows searching with something other than a single strategy, such as the best ordering found by
+/// `optimal_payoff` for each amount, or with lump sums paid on top. The search assumes that paying more never
+/// finishes later, and returns an amount that meets the target while a cent less doesn't.
+pub fn required_extra_payment_with(
+	loans: &[&Loan],
+	target_period: i64,
+	mut pay: impl FnMut(Money) -> Result<PayoffResult, Error>,
+) -> Result<RequiredExtraPayment, Error> {
+	let unreachable = || {
+		Error::OtherError(format!(
+			"no extra amount pays the loans off by period {}",
+			target_period
+		))
+	};
+	if target_period < 1 {
+		return Err(unreachable());
+	}
+	let mut try_amount = |extra_amount: Money| -> Result<Option<PayoffResult>, Error> {
+		match pay(extra_amount) {
+			Ok(result) if result.total_periods <= target_period => Ok(Some(result)),
+			Ok(_) | Err(Error::LoanGoesToInf) => Ok(None),
+			Err(e) => Err(e),
+		}
+	};
+
+	if let Some(result) = try_amount(Money::ZERO)? {
+		return Ok(RequiredExtraPayment {
+			extra_amount: Money::ZERO,
+			result,
+		});
+	}
+
+	// paying the whole balance as extra is usually enough, doubling covers interest, fees and penalties on top
+	const MAX_DOUBLINGS: usize = 20;
+	let total: Money = loans.iter().map(|l| l.initial_value).sum();
+	let mut low = Money::ZERO;
+	let mut high = total.max(Money::from_cents(1));
+	let mut best = None;
+	for _ in 0..MAX_DOUBLINGS {
+		best = try_amount(high)?;
+		if best.is_some() {
+			break;
+		}
+		low = high;
+		high = high * 2;
+	}
+	let mut best = best.ok_or_else(unreachable)?;
+
+	// low never meets the target and high always does
+	while (high - low).cents() > 1 {
+		let middle = Money::from_cents(low.cents() + (high - low).cents() / 2);
+		match try_amount(middle)? {
+			Some(result) => {
+				high = middle;
+				best = result;
+			}
+			None => low = middle,
+		}
+	}
+	Ok(RequiredExtraPayment {
+		extra_amount: high,
+		result: best,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{DebtAvalanche, Prepayment};
+	use test_case::test_case;
+
+	fn loan(initial_value: i64, rate: f64, number_of_payments: i64) -> Loan {
+		let mut loan = Loan {
+			initial_value: Money::from_cents(initial_value),
+			rate,
+			number_of_payments,
+			..Default::default()
+		};
+		// rounded up so the regular payments alone pay it off in time
+		loan.payment_amount =
+			Money::from_f64((loan.calculate_payment_amount() * 100.0).ceil() / 100.0);
+		loan
+	}
+
+	#[test_case(36 => 0)]
+	#[test_case(24 => 6929)]
+	#[test_case(12 => 35957)]
+	#[test_case(2 => 328806)]
+	#[test_case(1 => 680527)]
+	fn minimal_to_the_cent(target_period: i64) -> i64 {
+		let loans = [loan(500000, 0.01, 36), loan(200000, 0.005, 24)];
+		let loans: Vec<&Loan> = loans.iter().collect();
+		let required = required_extra_payment(&loans, target_period, &DebtAvalanche).unwrap();
+		assert!(required.result.total_periods <= target_period);
+
+		let cent_less = required.extra_amount - Money::from_cents(1);
+		if !cent_less.is_negative() {
+			let result = pay_loans_with_strategy(&loans, cent_less, &DebtAvalanche);
+			assert!(result.map_or(true, |r| r.total_periods > target_period));
+		}
+		required.extra_amount.cents()
+	}
+
+	#[test]
+	fn unreachable_target() {
+		let mut capped = loan(500000, 0.01, 36);
+		capped.prepayment = Some(Prepayment {
+			penalty: None,
+			annual_cap: Some(0.1),
+		});
+		let loans = [&capped];
+		assert!(matches!(
+			required_extra_payment(&loans, 6, &DebtAvalanche),
+			Err(Error::OtherError(_))
+		));
+		assert!(matches!(
+			required_extra_payment(&loans, 0, &DebtAvalanche),
+			Err(Error::OtherError(_))
+		));
+	}
+}
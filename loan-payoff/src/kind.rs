@@ -0,0 +1,176 @@
+use crate::Money;
+use serde::Deserialize;
+use std::fmt;
+
+/// How the loan's regular payment is worked out
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum LoanKind {
+	/// Equal payments that pay the loan off in `number_of_payments`, so `payment_amount` has to match the term
+	#[default]
+	Installment,
+	/// A credit card or line of credit with no term, the minimum payment is worked out from the balance every
+	/// time a payment is due and shrinks as the balance falls
+	Revolving(MinimumPayment),
+	/// Interest-only payments for the first `payments`, then equal payments that pay the loan off in the rest of
+	/// `number_of_payments`, which is what `payment_amount` is
+	InterestOnly {
+		payments: i64,
+	},
+	/// Payments worked out as if the loan ran for `amortization_payments`, with whatever is left paid in one go
+	/// with the last of `number_of_payments`
+	Balloon {
+		amortization_payments: i64,
+	},
+	Student(StudentLoan),
+}
+
+/// Minimum payment of a revolving loan, a share of the balance with or without the interest, and at least `floor`
+///
+/// The default is the usual card formula, 1% of the balance plus interest and at least $25.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MinimumPayment {
+	/// Fraction of the balance, 0.01 for 1%
+	pub percent_of_balance: f64,
+	/// Whether the interest since the last payment is added on top
+	pub plus_interest: bool,
+	pub floor: Money,
+}
+
+/// A student loan that isn't paid until `repayment_start`, through school, grace and deferment
+///
+/// Unsubsidized loans accrue interest on the principal in the meantime without compounding, and whatever of it
+/// is still unpaid is added to the principal at each capitalization and when repayment starts. Paying that
+/// interest before it capitalizes saves the interest on it. The payment is worked out when repayment starts, to
+/// pay off the balance then in `number_of_payments`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StudentLoan {
+	/// 1-based month of the first payment
+	pub repayment_start: i64,
+	pub accrual: Accrual,
+	/// 1-based months unpaid interest is added to the principal at the start of, on top of when repayment starts
+	pub capitalization: Vec<i64>,
+}
+
+/// Whether a student loan accrues interest before repayment starts
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Accrual {
+	/// The interest is paid for until repayment starts
+	Subsidized,
+	#[default]
+	Unsubsidized,
+}
+
+impl StudentLoan {
+	/// Whether a 1-based month is before repayment starts
+	pub fn is_deferred(&self, month: i64) -> bool {
+		month < self.repayment_start
+	}
+
+	/// Whether unpaid interest is added to the principal at the start of a 1-based month
+	pub fn capitalizes(&self, month: i64) -> bool {
+		month == self.repayment_start || self.capitalization.contains(&month)
+	}
+
+	/// Balance when repayment starts if nothing is paid before then, with interest at a monthly `rate`
+	pub fn balance_at_repayment(&self, initial_value: Money, rate: f64) -> Money {
+		let mut principal = initial_value;
+		let mut unpaid_interest = Money::ZERO;
+		for month in 1..self.repayment_start {
+			if self.capitalizes(month) {
+				principal += std::mem::replace(&mut unpaid_interest, Money::ZERO);
+			}
+			if self.accrual == Accrual::Unsubsidized {
+				unpaid_interest += principal.mul_rate(rate);
+			}
+		}
+		principal + unpaid_interest
+	}
+}
+
+impl Default for MinimumPayment {
+	fn default() -> MinimumPayment {
+		MinimumPayment {
+			percent_of_balance: 0.01,
+			plus_interest: true,
+			floor: Money::from_cents(2500),
+		}
+	}
+}
+
+impl MinimumPayment {
+	/// Minimum payment on a balance with `interest` added since the last payment, never more than clears it
+	pub fn amount(&self, balance: Money, interest: Money) -> Money {
+		let mut amount = balance.mul_rate(self.percent_of_balance);
+		if self.plus_interest {
+			amount += interest;
+		}
+		amount.max(self.floor).min(balance + interest)
+	}
+}
+
+impl fmt::Display for MinimumPayment {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"{}% of the balance",
+			crate::round_to_decimals(self.percent_of_balance * 100.0, 3)
+		)?;
+		if self.plus_interest {
+			write!(f, " plus interest")?;
+		}
+		write!(f, ", at least ${}", self.floor)
+	}
+}
+
+impl fmt::Display for Accrual {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Accrual::Subsidized => write!(f, "subsidized"),
+			Accrual::Unsubsidized => write!(f, "unsubsidized"),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use test_case::test_case;
+
+	#[test_case(300000, 4500, true => 7500)]
+	#[test_case(300000, 4500, false => 3000)]
+	#[test_case(100000, 1500, true => 2500)]
+	#[test_case(2000, 30, true => 2030)]
+	fn amount(balance: i64, interest: i64, plus_interest: bool) -> i64 {
+		let minimum = MinimumPayment {
+			plus_interest,
+			..Default::default()
+		};
+		minimum
+			.amount(Money::from_cents(balance), Money::from_cents(interest))
+			.cents()
+	}
+
+	#[test_case(Accrual::Subsidized, vec![] => 1000000)]
+	#[test_case(Accrual::Unsubsidized, vec![] => 1030000)]
+	#[test_case(Accrual::Unsubsidized, vec![4] => 1030225)]
+	#[test_case(Accrual::Unsubsidized, vec![1] => 1030000)]
+	fn balance_at_repayment(accrual: Accrual, capitalization: Vec<i64>) -> i64 {
+		let student = StudentLoan {
+			repayment_start: 7,
+			accrual,
+			capitalization,
+		};
+		student
+			.balance_at_repayment(Money::from_cents(1000000), 0.005)
+			.cents()
+	}
+
+	#[test]
+	fn display() {
+		assert_eq!(
+			MinimumPayment::default().to_string(),
+			"1% of the balance plus interest, at least $25.00"
+		);
+	}
+}
@@ -1,32 +1,148 @@
-use log;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
+mod allocation;
+mod extra_payments;
+mod fee;
+pub mod finance;
+mod frequency;
+mod goal;
+mod kind;
+mod money;
+mod mortgage;
+mod observer;
+mod optimizer;
+mod prepayment;
+mod rate;
+mod rate_schedule;
+mod schedule;
+mod simulation;
+mod strategy;
+mod validation;
+
+pub use allocation::{Allocation, AllocationRule, Share};
+pub use extra_payments::{ExtraPaymentRange, ExtraPayments, LumpSum};
+pub use fee::{Fee, FeeFrequency};
+pub use frequency::{Compounding, PaymentFrequency, Timeline};
+pub use goal::{required_extra_payment, required_extra_payment_with, RequiredExtraPayment};
+pub use kind::{Accrual, LoanKind, MinimumPayment, StudentLoan};
+pub use money::{Money, ParseMoneyError};
+pub use mortgage::Mortgage;
+pub use observer::{PayoffObserver, SilentObserver};
+pub use optimizer::{
+	optimal_payoff, optimal_payoff_with_allocations, optimal_payoff_with_observer,
+};
+pub use prepayment::{PenaltyCharge, Prepayment, PrepaymentPenalty};
+pub use rate::Rate;
+pub use rate_schedule::{PaymentOnRateChange, Promotion, RateChange, RateSchedule};
+pub use schedule::{Schedule, ScheduleEntry, SchedulePeriod};
+pub use strategy::{
+	DebtAvalanche, DebtSnowball, FixedOrder, HighestPaymentFirst, PayoffStrategy,
+	PromotionDeadlines, Split,
+};
+pub use validation::{ValidationIssue, ValidationPolicy};
+
 #[derive(Clone, PartialEq, Deserialize, Default)]
+#[serde(try_from = "LoanRecord")]
 pub struct Loan {
 	pub name: String,
-	pub initial_value: f64,
+	pub initial_value: Money,
+	/// Nominal monthly rate, the annual rate divided by 12, whatever the compounding and payment frequency
+	///
+	/// `annual_rate` and `set_rate` convert to and from an APR or APY.
 	pub rate: f64,
+	/// Number of payments at `payment_frequency`, revolving loans have no term and leave it at 0
 	pub number_of_payments: i64,
-	pub payment_amount: f64,
+	/// Regular payment, for revolving loans the first minimum payment
+	pub payment_amount: Money,
+	#[serde(default)]
+	pub compounding: Compounding,
+	#[serde(default)]
+	pub payment_frequency: PaymentFrequency,
+	/// Changes to `rate` over the life of the loan, `None` for a fixed rate
+	pub rate_schedule: Option<RateSchedule>,
+	/// Promotional rate before `rate` applies
+	pub promotion: Option<Promotion>,
+	pub kind: LoanKind,
+	/// PMI and escrow on top of `payment_amount`, which is only principal and interest
+	pub mortgage: Option<Mortgage>,
+	/// Penalty for paying off early and cap on extra principal, `None` for neither
+	pub prepayment: Option<Prepayment>,
+	/// Fees charged for as long as the loan has a balance
+	pub fees: Vec<Fee>,
+	/// How closely `payment_amount` has to match the other terms
+	pub validation_policy: ValidationPolicy,
+	/// Term worked out by `solve_for`, a number of payments rounded up leaves a smaller last payment
+	pub solved_term: Option<LoanTerm>,
 }
 
-pub struct OptimalPayoff {
+/// Outcome of paying off every loan in a single ordering
+///
+/// Per-loan vectors are indexed the same way as the loans passed in, not by position in the ordering.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PayoffResult {
 	pub ordering: Vec<usize>,
-	pub savings: f64,
+	/// Shares of the extra amount set aside for particular loans, empty when it all follows the ordering
+	pub allocation: Allocation,
+	/// Whether deferred-interest balances were moved to the front just in time to clear them
+	pub promotion_deadlines: bool,
 	pub is_debt_snowball: bool,
-	pub savings_over_debt_snowball: f64,
+	/// Cost of each loan when only the regular payments are made
+	pub expected_costs: Vec<Money>,
+	/// Cost of each loan when paid off using the extra amount
+	pub actual_costs: Vec<Money>,
+	/// Period in which each loan reached a zero balance
+	pub payoff_periods: Vec<i64>,
+	/// PMI paid on each loan, part of `actual_costs`
+	pub pmi_costs: Vec<Money>,
+	/// Last period each loan was charged PMI in, 0 for none
+	pub pmi_periods: Vec<i64>,
+	/// Prepayment penalty charged on each loan, part of `actual_costs`
+	pub penalty_costs: Vec<Money>,
+	/// Fees charged on each loan, part of `actual_costs`
+	pub fee_costs: Vec<Money>,
+	/// How much more than its regular payment the last scheduled payment of each loan came to, less when it is
+	/// negative, and 0 for loans paid off before then
+	pub final_payment_adjustments: Vec<Money>,
+	pub total_periods: i64,
+	/// Length of the periods, 12 unless some loans are paid more often than monthly
+	pub periods_per_year: i64,
+	pub expected_costs_total: Money,
+	pub actual_costs_total: Money,
+	pub savings_total: Money,
+}
+
+impl PayoffResult {
+	pub fn savings(&self, loan: usize) -> Money {
+		self.expected_costs[loan] - self.actual_costs[loan]
+	}
+}
+
+/// One of the four terms of an installment loan, any three of which decide the fourth
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoanTerm {
+	InitialValue,
+	Rate,
+	NumberOfPayments,
+	PaymentAmount,
 }
 
-const DEFAULT_ROUNDING_PLACES: u8 = 4;
+pub struct OptimalPayoff {
+	/// Result for the ordering with the most savings
+	pub best: PayoffResult,
+	/// Result for the best debt snowball ordering, if any of them converged
+	pub debt_snowball: Option<PayoffResult>,
+	pub savings_over_debt_snowball: Money,
+}
 
 impl Loan {
 	pub fn new(
 		name: String,
-		initial_value: f64,
+		initial_value: Money,
 		rate: f64,
 		number_of_payments: i64,
-		payment_amount: f64,
+		payment_amount: Money,
 	) -> Loan {
 		Loan {
 			name,
@@ -34,99 +150,772 @@ impl Loan {
 			rate,
 			number_of_payments,
 			payment_amount,
+			compounding: Compounding::Monthly,
+			payment_frequency: PaymentFrequency::Monthly,
+			rate_schedule: None,
+			promotion: None,
+			kind: LoanKind::Installment,
+			mortgage: None,
+			prepayment: None,
+			fees: Vec::new(),
+			validation_policy: ValidationPolicy::Strict,
+			solved_term: None,
 		}
 	}
 
-	pub fn calculate_payment_amount(&self) -> f64 {
-		self.initial_value
-			* (self.rate * f64::powf(1.0 + self.rate, self.number_of_payments as f64))
-			/ (f64::powf(1.0 + self.rate, self.number_of_payments as f64) - 1.0)
+	/// A revolving loan like a credit card, with the minimum payment for the balance as its payment
+	pub fn revolving(name: String, balance: Money, rate: f64, minimum: MinimumPayment) -> Loan {
+		let mut loan = Loan {
+			name,
+			initial_value: balance,
+			rate,
+			kind: LoanKind::Revolving(minimum),
+			..Default::default()
+		};
+		loan.payment_amount = minimum.amount(balance, balance.mul_rate(loan.payment_rate()));
+		loan
+	}
+
+	/// Every issue with the loan's fields, including a payment that doesn't pay it off in its number of payments
+	pub fn validate(&self) -> Result<(), Vec<ValidationIssue>> {
+		let issues = validation::issues(self);
+		if issues.is_empty() {
+			Ok(())
+		} else {
+			Err(issues)
+		}
+	}
+
+	/// An installment loan from the terms that are known, working out the one that is `None`
+	pub fn from_terms(
+		name: String,
+		initial_value: Option<Money>,
+		rate: Option<f64>,
+		number_of_payments: Option<i64>,
+		payment_amount: Option<Money>,
+	) -> Result<Loan, String> {
+		let missing = missing_term(
+			&name,
+			[
+				(initial_value.is_none(), LoanTerm::InitialValue),
+				(rate.is_none(), LoanTerm::Rate),
+				(number_of_payments.is_none(), LoanTerm::NumberOfPayments),
+				(payment_amount.is_none(), LoanTerm::PaymentAmount),
+			],
+		)?;
+		let mut loan = Loan::new(
+			name,
+			initial_value.unwrap_or(Money::ZERO),
+			rate.unwrap_or(0.0),
+			number_of_payments.unwrap_or(0),
+			payment_amount.unwrap_or(Money::ZERO),
+		);
+		if let Some(term) = missing {
+			loan.solve_for(term)?;
+		}
+		Ok(loan)
+	}
+
+	/// Works out `term` from the other three and sets it
+	///
+	/// The rate is the one that gives the payment at the loan's compounding and payment frequency. A balance and
+	/// payment seldom come out at a whole number of payments, so the term is rounded to the nearest one when the
+	/// payment for it is within five cents of the payment, and up otherwise, which leaves a smaller last payment.
+	/// Only installment loans with a fixed rate have a single set of terms to solve.
+	pub fn solve_for(&mut self, term: LoanTerm) -> Result<(), String> {
+		if self.kind != LoanKind::Installment || self.has_variable_rate() {
+			return Err(format!(
+				"loan '{}' needs {}, it can only be worked out for installment loans with a fixed rate",
+				self.name, term
+			));
+		}
+		let name = self.name.clone();
+		let unsolvable = || format!("loan '{}' has no {} that fits its other terms", name, term);
+		let rate = self.payment_rate();
+		let number_of_payments = self.number_of_payments as f64;
+		let initial_value = self.initial_value.to_f64();
+		// paid out, so negative the way the finance functions count it
+		let payment = -self.payment_amount.to_f64();
+		let timing = finance::PaymentTiming::End;
+		match term {
+			LoanTerm::InitialValue => {
+				let initial_value = finance::pv(rate, number_of_payments, payment, 0.0, timing)
+					.filter(|&v| v > 0.0)
+					.ok_or_else(unsolvable)?;
+				self.initial_value = Money::from_f64(initial_value);
+			}
+			LoanTerm::Rate => {
+				let periodic = finance::rate(
+					number_of_payments,
+					payment,
+					initial_value,
+					0.0,
+					timing,
+					0.01,
+				)
+				.filter(|&r| r >= 0.0)
+				.ok_or_else(unsolvable)?;
+				let periods_per_year = self.payment_frequency.periods_per_year();
+				self.set_rate(Rate::from_periodic(
+					periodic,
+					periods_per_year,
+					self.compounding,
+				));
+			}
+			LoanTerm::NumberOfPayments => {
+				let exact = finance::nper(rate, payment, initial_value, 0.0, timing)
+					.filter(|&n| n > 0.0)
+					.ok_or_else(unsolvable)?;
+				let nearest = Loan {
+					number_of_payments: exact.round() as i64,
+					..self.clone()
+				};
+				self.number_of_payments = if nearest.number_of_payments > 0
+					&& within_five_cents(
+						Money::from_f64(nearest.calculate_payment_amount()),
+						self.payment_amount,
+					) {
+					nearest.number_of_payments
+				} else {
+					exact.ceil() as i64
+				};
+			}
+			LoanTerm::PaymentAmount => {
+				let payment_amount = self.calculate_payment_amount();
+				if !payment_amount.is_finite() {
+					return Err(unsolvable());
+				}
+				self.payment_amount = Money::from_f64(payment_amount);
+			}
+		}
+		self.solved_term = Some(term);
+		Ok(())
+	}
+
+	/// A student loan, with the payment that pays off its balance when repayment starts if nothing is paid before
+	pub fn student(
+		name: String,
+		initial_value: Money,
+		rate: f64,
+		number_of_payments: i64,
+		student: StudentLoan,
+	) -> Loan {
+		let mut loan = Loan::new(name, initial_value, rate, number_of_payments, Money::ZERO);
+		loan.kind = LoanKind::Student(student);
+		loan.payment_amount = loan.student_payment_amount();
+		loan
+	}
+
+	/// Payment that pays off a student loan's balance when repayment starts, rounded up to the cent
+	fn student_payment_amount(&self) -> Money {
+		let repayment = self.repayment_loan();
+		rate_schedule::reamortized_payment(
+			&repayment,
+			repayment.rate,
+			repayment.initial_value,
+			repayment.number_of_payments,
+		)
+	}
+
+	/// The loan as it is when its regular payments start, only a student loan is any different
+	pub fn repayment_loan(&self) -> Loan {
+		match &self.kind {
+			LoanKind::Student(student) => Loan {
+				initial_value: student.balance_at_repayment(self.initial_value, self.rate_per(12)),
+				kind: LoanKind::Installment,
+				..self.clone()
+			},
+			_ => self.clone(),
+		}
+	}
+
+	/// The loan's rate as an APR with its compounding
+	pub fn annual_rate(&self) -> Rate {
+		Rate::from_apr(self.rate * 12.0, self.compounding)
+	}
+
+	/// Sets both the rate and the compounding
+	pub fn set_rate(&mut self, rate: Rate) {
+		self.rate = rate.apr() / 12.0;
+		self.compounding = rate.compounding();
+	}
+
+	/// Effective interest rate over one of `periods_per_year` equal periods, given the compounding
+	pub fn rate_per(&self, periods_per_year: i64) -> f64 {
+		self.rate_per_at(self.rate, periods_per_year)
+	}
+
+	/// Same as `rate_per`, for a monthly rate the loan has some other time, like after a rate change
+	pub fn rate_per_at(&self, rate: f64, periods_per_year: i64) -> f64 {
+		// skip the conversion so monthly loans keep exactly the rate they were given
+		if periods_per_year == 12 && self.compounding == Compounding::Monthly {
+			return rate;
+		}
+		Rate::from_apr(rate * 12.0, self.compounding).periodic(periods_per_year)
+	}
+
+	/// Whether the rate changes over the life of the loan, with a rate schedule or a promotion
+	pub fn has_variable_rate(&self) -> bool {
+		self.rate_schedule.is_some() || self.promotion.is_some()
+	}
+
+	/// Whether every regular payment is `payment_amount` until the loan is paid off in `number_of_payments`, with
+	/// no PMI that drops off part way, no fees and no last payment that is trued up or smaller
+	pub fn has_level_payments(&self) -> bool {
+		!self.has_variable_rate()
+			&& self.kind == LoanKind::Installment
+			&& self.fees.is_empty()
+			&& self.mortgage.as_ref().is_none_or(|m| m.pmi.is_zero())
+			&& self.validation_policy == ValidationPolicy::Strict
+			&& validation::expected_payment_amount(self)
+				.is_some_and(|expected| within_five_cents(expected, self.payment_amount))
+	}
+
+	/// How much more the last scheduled payment is than `payment_amount` for the loan to be paid off on time
+	///
+	/// Negative when the payments are more than needed, so the last one is smaller or the loan is paid off early.
+	/// `None` for loans whose payment isn't trued up that way: revolving, balloon and student loans, and loans with a
+	/// rate schedule or a promotion that ends part way. The simulation only applies it outside of
+	/// `ValidationPolicy::Strict`.
+	pub fn final_payment_adjustment(&self) -> Option<Money> {
+		validation::final_payment_adjustment(self)
+	}
+
+	/// The first payment as it is on the statement, with any PMI and escrow
+	pub fn statement_payment(&self) -> Money {
+		match &self.mortgage {
+			Some(mortgage) => {
+				self.payment_amount + mortgage.pmi_for(self.initial_value) + mortgage.escrow
+			}
+			None => self.payment_amount,
+		}
+	}
+
+	/// Number of payments `payment_amount` pays the loan off in, `number_of_payments` unless the loan starts out
+	/// interest only or ends in a balloon
+	pub fn amortization_payments(&self) -> i64 {
+		match self.kind {
+			LoanKind::InterestOnly { payments } => self.number_of_payments - payments,
+			LoanKind::Balloon {
+				amortization_payments,
+			} => amortization_payments,
+			_ => self.number_of_payments,
+		}
+	}
+
+	/// Whether a 1-based payment is still at the promotional rate
+	pub fn is_promotional(&self, payment: i64) -> bool {
+		self.promotion
+			.as_ref()
+			.is_some_and(|p| payment <= p.payments)
 	}
 
-	// Returns the amount paid, remaining_amount
-	pub fn pay_loan(&self, present_value: f64, payment_amount: f64) -> (f64, f64) {
-		if approx_equal(payment_amount, 0.0, DEFAULT_ROUNDING_PLACES) {
-			println!("ERR: paying {}", payment_amount);
-			return (0.0, 0.0);
+	/// Monthly rate for a 1-based payment, following the promotion and rate schedule if there are any
+	pub fn rate_for_payment(&self, payment: i64) -> f64 {
+		match &self.promotion {
+			Some(promotion) if payment <= promotion.payments => promotion.rate,
+			_ => self.standard_rate_for_payment(payment),
 		}
+	}
+
+	/// Monthly rate for a 1-based payment as if there were no promotion, what deferred interest is charged at
+	pub fn standard_rate_for_payment(&self, payment: i64) -> f64 {
+		self.rate_schedule
+			.as_ref()
+			.map_or(self.rate, |s| s.rate_for_payment(self.rate, payment))
+	}
 
-		let mut present_value = present_value;
-		let interest = round_to_currency(present_value * self.rate);
-		present_value += interest;
-		let mut payment_amount_this_period = payment_amount;
-		if payment_amount_this_period > present_value {
-			payment_amount_this_period = round_to_currency(present_value);
+	/// Lowest monthly rate the loan has from a 1-based payment on
+	pub fn lowest_rate_from(&self, payment: i64) -> f64 {
+		let promotion_payments = self.promotion.as_ref().map_or(0, |p| p.payments);
+		let after_promotion = payment.max(promotion_payments + 1);
+		let lowest = self.rate_schedule.as_ref().map_or(self.rate, |s| {
+			s.lowest_rate_from(self.rate, after_promotion)
+		});
+		match &self.promotion {
+			Some(promotion) if payment <= promotion.payments => lowest.min(promotion.rate),
+			_ => lowest,
 		}
-		present_value = round_to_currency(present_value);
-		//println!("BEFORE: {}", self);
-		//println!("paying {} .. count={}, amount_due={}", payment_amount, count, calculated_payment_amount);
-		present_value -= payment_amount_this_period;
-		present_value = round_to_currency(present_value);
-		//self.number_of_payments -= 1;
+	}
 
-		//println!("AFTER: {}", self);
+	/// Interest rate between two regular payments
+	pub fn payment_rate(&self) -> f64 {
+		self.rate_per(self.payment_frequency.periods_per_year())
+	}
 
-		(payment_amount_this_period, present_value)
+	/// Payment that pays `initial_value` off in `number_of_payments`, NaN when there are no payments
+	pub fn calculate_payment_amount(&self) -> f64 {
+		finance::pmt(
+			self.payment_rate(),
+			self.number_of_payments as f64,
+			-self.initial_value.to_f64(),
+			0.0,
+			finance::PaymentTiming::End,
+		)
+		.unwrap_or(f64::NAN)
 	}
+
+	/// Adds one payment period of interest and pays, returns the amount paid and the remaining amount
+	pub fn pay_loan(&self, present_value: Money, payment_amount: Money) -> (Money, Money) {
+		// A zero payment still carries the balance forward with its interest, as in a deferment or promotion.
+		// Treating it as paid off would let an ordering skip a whole loan.
+		self.pay_loan_at(self.payment_rate(), present_value, payment_amount)
+	}
+
+	/// Same as `pay_loan`, but adds interest at the rate given, used for periods that aren't payment periods
+	pub(crate) fn pay_loan_at(
+		&self,
+		rate: f64,
+		present_value: Money,
+		payment_amount: Money,
+	) -> (Money, Money) {
+		let present_value = present_value + present_value.mul_rate(rate);
+		let payment_amount_this_period = payment_amount.min(present_value);
+
+		(
+			payment_amount_this_period,
+			present_value - payment_amount_this_period,
+		)
+	}
+}
+
+/// A loan as it is written in a CSV file or other serde input
+///
+/// The rate can be given as the monthly `rate`, or as an `apr` or `apy` so nobody has to work out the monthly rate
+/// by hand. Exactly one of them has to be there.
+#[derive(Deserialize)]
+struct LoanRecord {
+	name: String,
+	/// Any one of `initial_value`, the rate, `number_of_payments` and `payment_amount` can be left out and is
+	/// worked out from the others
+	#[serde(default)]
+	initial_value: Option<Money>,
+	#[serde(default)]
+	rate: Option<f64>,
+	#[serde(default)]
+	apr: Option<f64>,
+	#[serde(default)]
+	apy: Option<f64>,
+	/// Can be left out for revolving loans
+	#[serde(default)]
+	number_of_payments: Option<i64>,
+	/// Can be left out for revolving loans, which start at their minimum payment
+	#[serde(default)]
+	payment_amount: Option<Money>,
+	#[serde(default)]
+	compounding: Compounding,
+	#[serde(default)]
+	payment_frequency: PaymentFrequency,
+	/// Number of payments at `promo_apr`, there is no promotion without it
+	#[serde(default)]
+	promo_payments: Option<i64>,
+	#[serde(default)]
+	promo_apr: Option<f64>,
+	#[serde(default)]
+	deferred_interest: Option<bool>,
+	/// Fraction of the balance in the minimum payment, which makes the loan revolving
+	#[serde(default)]
+	minimum_percent: Option<f64>,
+	#[serde(default)]
+	minimum_plus_interest: Option<bool>,
+	#[serde(default)]
+	minimum_floor: Option<Money>,
+	/// Number of interest-only payments at the start of the loan
+	#[serde(default)]
+	interest_only_payments: Option<i64>,
+	/// Term the payment is worked out for when the loan ends in a balloon after `number_of_payments`
+	#[serde(default)]
+	balloon_amortization_payments: Option<i64>,
+	/// Month of the first payment of a student loan
+	#[serde(default)]
+	repayment_start: Option<i64>,
+	#[serde(default)]
+	accrual: Option<Accrual>,
+	/// Months unpaid student loan interest capitalizes at the start of, separated by `;`
+	#[serde(default)]
+	capitalization: Option<String>,
+	/// Value of the home, which makes the loan a mortgage
+	#[serde(default)]
+	home_value: Option<Money>,
+	#[serde(default)]
+	pmi: Option<Money>,
+	/// Loan-to-value ratio PMI stops at, 80% when left out
+	#[serde(default)]
+	pmi_removal_ltv: Option<f64>,
+	#[serde(default)]
+	escrow: Option<Money>,
+	/// Prepayment penalty as a fraction of the balance paid off early
+	#[serde(default)]
+	penalty_percent: Option<f64>,
+	/// Prepayment penalty as months of interest on the balance paid off early
+	#[serde(default)]
+	penalty_months_interest: Option<i64>,
+	/// Months from the start of the loan the penalty applies in, all of them when left out
+	#[serde(default)]
+	penalty_window_months: Option<i64>,
+	/// Most extra principal a year as a fraction of the initial value
+	#[serde(default)]
+	annual_prepayment_cap: Option<f64>,
+	#[serde(default)]
+	annual_fee: Option<Money>,
+	/// Month the annual fee is first charged in, the first month when left out
+	#[serde(default)]
+	annual_fee_month: Option<i64>,
+	#[serde(default)]
+	monthly_fee: Option<Money>,
+	#[serde(default)]
+	late_fee: Option<Money>,
+	/// Months a late fee is charged in, separated by `;`
+	#[serde(default)]
+	late_fee_months: Option<String>,
 }
 
+impl TryFrom<LoanRecord> for Loan {
+	type Error = String;
+
+	fn try_from(record: LoanRecord) -> Result<Loan, String> {
+		let rate = match (record.rate, record.apr, record.apy) {
+			(None, None, None) => None,
+			(Some(rate), None, None) => Some(rate),
+			(None, Some(apr), None) => Some(apr / 12.0),
+			(None, None, Some(apy)) => Some(Rate::from_apy(apy, record.compounding).apr() / 12.0),
+			_ => {
+				return Err(format!(
+					"loan '{}' needs at most one of rate, apr or apy",
+					record.name
+				))
+			}
+		};
+		let promotion = record.promo_payments.map(|payments| Promotion {
+			payments,
+			rate: record.promo_apr.unwrap_or(0.0) / 12.0,
+			deferred_interest: record.deferred_interest.unwrap_or(false),
+		});
+		let mut loan = if let Some(percent_of_balance) = record.minimum_percent {
+			let minimum = MinimumPayment {
+				percent_of_balance,
+				plus_interest: record.minimum_plus_interest.unwrap_or(true),
+				floor: record.minimum_floor.unwrap_or(Money::ZERO),
+			};
+			let (Some(balance), Some(rate)) = (record.initial_value, rate) else {
+				return Err(format!(
+					"revolving loan '{}' needs initial_value and one of rate, apr or apy",
+					record.name
+				));
+			};
+			let mut loan = Loan::revolving(record.name.clone(), balance, rate, minimum);
+			loan.compounding = record.compounding;
+			loan.payment_frequency = record.payment_frequency;
+			loan.promotion = promotion;
+			if let Some(payment_amount) = record.payment_amount {
+				loan.payment_amount = payment_amount;
+			}
+			loan
+		} else {
+			let kind = match (
+				record.interest_only_payments,
+				record.balloon_amortization_payments,
+				record.repayment_start,
+			) {
+				(None, None, None) => LoanKind::Installment,
+				(Some(payments), None, None) => LoanKind::InterestOnly { payments },
+				(None, Some(amortization_payments), None) => LoanKind::Balloon {
+					amortization_payments,
+				},
+				(None, None, Some(repayment_start)) => {
+					let capitalization =
+						parse_months(&record.name, "capitalization", &record.capitalization)?;
+					LoanKind::Student(StudentLoan {
+						repayment_start,
+						accrual: record.accrual.unwrap_or_default(),
+						capitalization,
+					})
+				}
+				_ => {
+					return Err(format!(
+						"loan '{}' can only be one of interest only, balloon or student",
+						record.name
+					))
+				}
+			};
+			let is_student = matches!(kind, LoanKind::Student(_));
+			let missing = missing_term(
+				&record.name,
+				[
+					(record.initial_value.is_none(), LoanTerm::InitialValue),
+					(rate.is_none(), LoanTerm::Rate),
+					(
+						record.number_of_payments.is_none(),
+						LoanTerm::NumberOfPayments,
+					),
+					// a student loan's payment isn't known until repayment starts, so it is fine to leave out
+					(
+						record.payment_amount.is_none() && !is_student,
+						LoanTerm::PaymentAmount,
+					),
+				],
+			)?;
+			let mut loan = Loan {
+				name: record.name.clone(),
+				initial_value: record.initial_value.unwrap_or(Money::ZERO),
+				rate: rate.unwrap_or(0.0),
+				number_of_payments: record.number_of_payments.unwrap_or(0),
+				payment_amount: record.payment_amount.unwrap_or(Money::ZERO),
+				compounding: record.compounding,
+				payment_frequency: record.payment_frequency,
+				rate_schedule: None,
+				promotion,
+				kind,
+				mortgage: None,
+				prepayment: None,
+				fees: Vec::new(),
+				validation_policy: ValidationPolicy::Strict,
+				solved_term: None,
+			};
+			match missing {
+				Some(term) => loan.solve_for(term)?,
+				None if record.payment_amount.is_none() => {
+					loan.payment_amount = loan.student_payment_amount()
+				}
+				None => {}
+			}
+			loan
+		};
+		if let Some(home_value) = record.home_value {
+			loan.mortgage = Some(Mortgage {
+				home_value,
+				pmi: record.pmi.unwrap_or(Money::ZERO),
+				pmi_removal_ltv: record.pmi_removal_ltv.unwrap_or(0.8),
+				escrow: record.escrow.unwrap_or(Money::ZERO),
+			});
+		} else if record.pmi.is_some() || record.escrow.is_some() {
+			return Err(format!(
+				"loan '{}' needs a home_value for pmi or escrow",
+				loan.name
+			));
+		}
+		let charge = match (record.penalty_percent, record.penalty_months_interest) {
+			(None, None) => None,
+			(Some(percent), None) => Some(PenaltyCharge::PercentOfBalance(percent)),
+			(None, Some(months)) => Some(PenaltyCharge::MonthsOfInterest(months)),
+			(Some(_), Some(_)) => {
+				return Err(format!(
+					"loan '{}' needs at most one of penalty_percent or penalty_months_interest",
+					loan.name
+				))
+			}
+		};
+		if charge.is_some() || record.annual_prepayment_cap.is_some() {
+			loan.prepayment = Some(Prepayment {
+				penalty: charge.map(|charge| PrepaymentPenalty {
+					charge,
+					window_months: record.penalty_window_months,
+				}),
+				annual_cap: record.annual_prepayment_cap,
+			});
+		}
+		if let Some(amount) = record.annual_fee {
+			loan.fees.push(Fee {
+				amount,
+				frequency: FeeFrequency::Annual {
+					month: record.annual_fee_month.unwrap_or(1),
+				},
+			});
+		}
+		if let Some(amount) = record.monthly_fee {
+			loan.fees.push(Fee {
+				amount,
+				frequency: FeeFrequency::Monthly,
+			});
+		}
+		if let Some(amount) = record.late_fee {
+			let months = parse_months(&loan.name, "late_fee_months", &record.late_fee_months)?;
+			loan.fees.push(Fee {
+				amount,
+				frequency: FeeFrequency::Months(months),
+			});
+		}
+		Ok(loan)
+	}
+}
+
+/// The one term that is missing, if any, an error when there are more
+fn missing_term(loan: &str, terms: [(bool, LoanTerm); 4]) -> Result<Option<LoanTerm>, String> {
+	let missing: Vec<LoanTerm> = terms
+		.into_iter()
+		.filter(|&(is_missing, _)| is_missing)
+		.map(|(_, term)| term)
+		.collect();
+	match missing[..] {
+		[] => Ok(None),
+		[term] => Ok(Some(term)),
+		_ => Err(format!(
+			"loan '{}' is missing {}, only one of initial_value, rate, number_of_payments or payment_amount can be worked out from the others",
+			loan,
+			missing
+				.iter()
+				.map(LoanTerm::to_string)
+				.collect::<Vec<_>>()
+				.join(" and ")
+		)),
+	}
+}
+
+/// Reads a list of months separated by `;` from a CSV cell
+fn parse_months(loan: &str, column: &str, value: &Option<String>) -> Result<Vec<i64>, String> {
+	value
+		.as_deref()
+		.unwrap_or("")
+		.split(';')
+		.map(str::trim)
+		.filter(|m| !m.is_empty())
+		.map(|m| {
+			m.parse().map_err(|_| {
+				format!(
+					"loan '{}' has {} month '{}', expected a whole number",
+					loan, column, m
+				)
+			})
+		})
+		.collect()
+}
+
+/// Tries every ordering and picks the one with the most savings
+///
+/// With the `parallel` feature the orderings are spread across threads. The result is the same as trying them
+/// one at a time, ties go to the ordering that would have been tried first.
 pub fn pay_loans_all_orderings(
-	loans: &Vec<&Loan>,
-	extra_amount: f64,
+	loans: &[&Loan],
+	extra_payments: impl Into<ExtraPayments>,
 ) -> Result<OptimalPayoff, Error> {
-	// https://www.quickperm.org/
-	let mut ordering = vec![0; loans.len()];
-	for i in 0..ordering.len() {
-		ordering[i] = i
-	}
+	#[cfg(feature = "parallel")]
+	return pay_loans_all_orderings_parallel(loans, &extra_payments.into());
+	#[cfg(not(feature = "parallel"))]
+	pay_loans_all_orderings_with_observer(loans, extra_payments, &mut SilentObserver)
+}
 
-	// initial ordering
-	let mut best_savings = -1.0;
-	let mut best_ordering = Vec::new();
-	let mut best_debt_snowball_savings = -1.0; // Note: there can be multiple debt snowball (2 loans with same amount)
-	let mut is_snowball_best = false;
-	match pay_loans(&loans, extra_amount, &ordering) {
-		Ok((is_debt_snowball, _actual_costs_total, savings_total)) => {
-			if savings_total > best_savings {
-				best_savings = savings_total;
-				best_ordering = ordering.clone();
-				is_snowball_best = is_debt_snowball;
+#[cfg(feature = "parallel")]
+fn pay_loans_all_orderings_parallel(
+	loans: &[&Loan],
+	extra_payments: &ExtraPayments,
+) -> Result<OptimalPayoff, Error> {
+	use rayon::prelude::*;
+
+	// orderings are handed out in batches so they don't all have to be held in memory at once
+	const BATCH_SIZE: usize = 4096;
+
+	let mut best = BestOrderings::default();
+	let mut batch: Vec<Vec<usize>> = Vec::with_capacity(BATCH_SIZE);
+	let mut evaluate_batch = |batch: &mut Vec<Vec<usize>>| -> Result<(), Error> {
+		let results: Vec<Result<PayoffResult, Error>> = batch
+			.par_iter()
+			.map(|ordering| {
+				let strategy = FixedOrder(ordering.clone());
+				simulation::simulate(loans, extra_payments, &strategy, None, &mut SilentObserver)
+			})
+			.collect();
+		batch.clear();
+		// results come back in the order they were tried, so ties and errors resolve the same as one at a time
+		results.into_iter().try_for_each(|result| best.add(result))
+	};
+	for_each_ordering(loans.len(), |ordering| {
+		batch.push(ordering.to_vec());
+		if batch.len() == BATCH_SIZE {
+			evaluate_batch(&mut batch)?;
+		}
+		Ok(())
+	})?;
+	evaluate_batch(&mut batch)?;
+	best.finish()
+}
+
+/// Same as `pay_loans_all_orderings`, reporting every ordering tried to the observer
+///
+/// The orderings are always tried one at a time, even with the `parallel` feature.
+pub fn pay_loans_all_orderings_with_observer(
+	loans: &[&Loan],
+	extra_payments: impl Into<ExtraPayments>,
+	observer: &mut dyn PayoffObserver,
+) -> Result<OptimalPayoff, Error> {
+	let extra_payments = extra_payments.into();
+	let mut best = BestOrderings::default();
+	for_each_ordering(loans.len(), |ordering| {
+		let strategy = FixedOrder(ordering.to_vec());
+		best.add(observe_payoff(loans, &extra_payments, &strategy, observer))
+	})?;
+	best.finish()
+}
+
+/// Best results seen so far while trying orderings
+#[derive(Default)]
+struct BestOrderings {
+	best: Option<PayoffResult>,
+	// Note: there can be multiple debt snowball orderings (2 loans with same amount)
+	best_debt_snowball: Option<PayoffResult>,
+}
+
+impl BestOrderings {
+	fn add(&mut self, result: Result<PayoffResult, Error>) -> Result<(), Error> {
+		match result {
+			Ok(result) => {
+				if result.is_debt_snowball
+					&& self
+						.best_debt_snowball
+						.as_ref()
+						.is_none_or(|b| result.savings_total > b.savings_total)
+				{
+					self.best_debt_snowball = Some(result.clone());
+				}
+				if self
+					.best
+					.as_ref()
+					.is_none_or(|b| result.savings_total > b.savings_total)
+				{
+					self.best = Some(result);
+				}
+				Ok(())
 			}
-			if is_debt_snowball && savings_total > best_debt_snowball_savings {
-				best_debt_snowball_savings = savings_total;
+			Err(Error::LoanGoesToInf) => {
+				/* noop: if no loan orderings converge then we return this error below */
+				Ok(())
 			}
+			Err(e) => Err(e),
 		}
-		Err(Error::LoanGoesToInf) => { /* noop: if no loan orderings converge then we return this error below */
-		}
-		Err(e) => return Err(e),
 	}
 
-	let n = loans.len();
-	let mut p = vec![0; n + 1];
-	for i in 0..p.len() {
-		p[i] = i
+	fn finish(self) -> Result<OptimalPayoff, Error> {
+		let best = self.best.ok_or(Error::LoanGoesToInf)?;
+		let savings_over_debt_snowball = self
+			.best_debt_snowball
+			.as_ref()
+			.map_or(Money::ZERO, |s| best.savings_total - s.savings_total);
+		Ok(OptimalPayoff {
+			best,
+			debt_snowball: self.best_debt_snowball,
+			savings_over_debt_snowball,
+		})
 	}
+}
+
+/// Calls `f` with every ordering of `n` loans, stopping at the first error
+fn for_each_ordering(
+	n: usize,
+	mut f: impl FnMut(&[usize]) -> Result<(), Error>,
+) -> Result<(), Error> {
+	// https://www.quickperm.org/
+	let mut ordering: Vec<usize> = (0..n).collect();
+
+	// initial ordering
+	f(&ordering)?;
+
+	let mut p: Vec<usize> = (0..=n).collect();
 	let mut i = 1;
 	while i < n {
 		p[i] -= 1;
 		let j = if i % 2 == 0 { 0 } else { p[i] };
 		ordering.swap(j, i);
 
-		match pay_loans(&loans, extra_amount, &ordering) {
-			Ok((is_debt_snowball, _actual_costs_total, savings_total)) => {
-				if savings_total > best_savings {
-					best_savings = savings_total;
-					best_ordering = ordering.clone();
-					is_snowball_best = is_debt_snowball;
-				}
-				if is_debt_snowball && savings_total > best_debt_snowball_savings {
-					best_debt_snowball_savings = savings_total;
-				}
-			}
-			Err(Error::LoanGoesToInf) => { /* noop: if no loan orderings converge then we return this error below */
-			}
-			Err(e) => return Err(e),
-		}
+		f(&ordering)?;
 
 		i = 1;
 		while p[i] == 0 {
@@ -134,155 +923,84 @@ pub fn pay_loans_all_orderings(
 			i += 1;
 		} // end while (p[i] is equal to 0)
 	} // end while (i < N)
-
-	if best_savings < 0.0 {
-		return Err(Error::LoanGoesToInf);
-	}
-
-	Ok(OptimalPayoff {
-		ordering: best_ordering,
-		savings: best_savings,
-		is_debt_snowball: is_snowball_best,
-		savings_over_debt_snowball: round_to_currency(best_savings - best_debt_snowball_savings),
-	})
+	Ok(())
 }
 
+/// Pays the loans in the given ordering
+///
+/// `extra_payments` is either a `Money` amount paid every period or an `ExtraPayments` schedule.
 pub fn pay_loans(
-	loans: &Vec<&Loan>,
-	extra_amount: f64,
+	loans: &[&Loan],
+	extra_payments: impl Into<ExtraPayments>,
 	ordering: &[usize],
-) -> Result<(bool, f64, f64), Error> {
-	log::debug!("Pay loans {:?}", ordering);
-	let mut remaining_amounts = vec![0.0; loans.len()];
-	let mut actual_costs = vec![0.0; loans.len()];
-	let mut expected_costs = vec![0.0; loans.len()];
-	let mut extra_amount = extra_amount;
-
-	let mut is_debt_snowball = true;
-	let mut max_cost = 0.0;
-
-	let mut max_number_payments = 0;
-
-	for &i in ordering.iter() {
-		let payment_amount = round_to_currency(loans[i].calculate_payment_amount());
-		if !within_five_cents(payment_amount, loans[i].payment_amount) {
-			log::error!("loan '{}': calculated loan payment amount {} is not within 5 cents of given amount {}", loans[i].name, payment_amount, loans[i].payment_amount);
-			return Err(Error::InvalidLoan(i));
-		}
+) -> Result<PayoffResult, Error> {
+	pay_loans_with_strategy(loans, extra_payments, &FixedOrder(ordering.to_vec()))
+}
 
-		if max_cost > loans[i].initial_value {
-			is_debt_snowball = false;
-		} else {
-			max_cost = loans[i].initial_value;
-		}
+/// Pays the loans in whatever order the strategy picks each period
+pub fn pay_loans_with_strategy(
+	loans: &[&Loan],
+	extra_payments: impl Into<ExtraPayments>,
+	strategy: &dyn PayoffStrategy,
+) -> Result<PayoffResult, Error> {
+	pay_loans_with_observer(loans, extra_payments, strategy, &mut SilentObserver)
+}
 
-		if loans[i].number_of_payments > max_number_payments {
-			max_number_payments = loans[i].number_of_payments;
-		}
+/// Same as `pay_loans_with_strategy`, reporting progress to the observer
+pub fn pay_loans_with_observer(
+	loans: &[&Loan],
+	extra_payments: impl Into<ExtraPayments>,
+	strategy: &dyn PayoffStrategy,
+	observer: &mut dyn PayoffObserver,
+) -> Result<PayoffResult, Error> {
+	observe_payoff(loans, &extra_payments.into(), strategy, observer)
+}
 
-		remaining_amounts[i] = loans[i].initial_value;
-		expected_costs[i] =
-			round_to_currency(loans[i].payment_amount * loans[i].number_of_payments as f64);
-	}
-
-	let mut count = 0;
-	let original_extra_amount = extra_amount;
-	while ordering.iter().any(|&i| {
-		remaining_amounts[i] > 0.0
-			&& !approx_equal(remaining_amounts[i], 0.0, DEFAULT_ROUNDING_PLACES)
-	}) {
-		count += 1;
-
-		if count > max_number_payments {
-			for &ix in ordering.iter() {
-				log::error!(
-					"Loan={}, Remaining Amount={}",
-					loans[ix],
-					remaining_amounts[ix]
-				);
-			}
-			log::error!(
-				"Went too long, should have finished in at most {} periods",
-				max_number_payments
-			);
-			return Err(Error::LoanGoesToInf);
+pub(crate) fn observe_payoff(
+	loans: &[&Loan],
+	extra_payments: &ExtraPayments,
+	strategy: &dyn PayoffStrategy,
+	observer: &mut dyn PayoffObserver,
+) -> Result<PayoffResult, Error> {
+	let result = simulation::simulate(loans, extra_payments, strategy, None, observer);
+	match &result {
+		Ok(result) => observer.ordering_paid_off(loans, result),
+		Err(e) => {
+			let balances: Vec<Money> = loans.iter().map(|l| l.initial_value).collect();
+			let rates: Vec<f64> = loans.iter().map(|l| l.rate_for_payment(1)).collect();
+			observer.ordering_failed(loans, &strategy.ordering(loans, &balances, &rates), e)
 		}
+	}
+	result
+}
 
-		let mut extra_amount_this_period = extra_amount;
-		for &ix in ordering.iter() {
-			if remaining_amounts[ix] > 0.0
-				&& !approx_equal(remaining_amounts[ix], 0.0, DEFAULT_ROUNDING_PLACES)
-			{
-				let amount_to_pay = loans[ix].payment_amount + extra_amount_this_period;
-
-				log::trace!(
-					"BEFORE {}: {}, remaining={}",
-					count,
-					loans[ix],
-					remaining_amounts[ix]
-				);
-				let (amount_paid_this_period, remaining_amount) =
-					loans[ix].pay_loan(remaining_amounts[ix], amount_to_pay);
-				log::trace!(
-					"AFTER {}: {}, remaining={}",
-					count,
-					loans[ix],
-					remaining_amount
-				);
-
-				remaining_amounts[ix] = remaining_amount;
-				extra_amount_this_period = amount_paid_this_period - amount_to_pay;
-				log::trace!("paying {} .. count={}", amount_paid_this_period, count);
-				actual_costs[ix] = round_to_currency(actual_costs[ix] + amount_paid_this_period);
-
-				// If the loan goes to 0 after paying, add the monthly payment to extra_amount (after paying all loans)
-				if approx_equal(remaining_amounts[ix], 0.0, DEFAULT_ROUNDING_PLACES) {
-					// Note: we can update extra_amount directly because it is not used until next period
-					extra_amount = round_to_currency(extra_amount + loans[ix].payment_amount);
-				}
-			}
+/// Pays the loans using the strategy and records every period along the way
+pub fn amortization_schedule(
+	loans: &[&Loan],
+	extra_payments: impl Into<ExtraPayments>,
+	strategy: &dyn PayoffStrategy,
+) -> Result<Schedule, Error> {
+	let mut schedule = Schedule::default();
+	let result = simulation::simulate(
+		loans,
+		&extra_payments.into(),
+		strategy,
+		Some(&mut schedule),
+		&mut SilentObserver,
+	)?;
+	schedule.ordering = result.ordering;
+	Ok(schedule)
+}
+
+impl fmt::Display for LoanTerm {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			LoanTerm::InitialValue => write!(f, "initial_value"),
+			LoanTerm::Rate => write!(f, "rate"),
+			LoanTerm::NumberOfPayments => write!(f, "number_of_payments"),
+			LoanTerm::PaymentAmount => write!(f, "payment_amount"),
 		}
 	}
-
-	let mut expected_costs_total = 0.0;
-	let mut actual_costs_total = 0.0;
-	let mut savings_total = 0.0;
-	for &i in ordering.iter() {
-		expected_costs_total += expected_costs[i];
-		actual_costs_total += actual_costs[i];
-		savings_total += expected_costs[i] - actual_costs[i];
-		// println!("{} - EXPECTED=${}", loans[i].name, expected_costs[i]);
-		// println!("{} - ACTUAL=${}", loans[i].name, actual_costs[i]);
-		// println!("{} - By paying an extra ${}, you saved ${}", loans[i].name, original_extra_amount, round_to_currency(expected_costs[i]-actual_costs[i]));
-	}
-	println!(
-		"{}",
-		ordering
-			.iter()
-			.map(|&i| loans[i].name.as_ref())
-			.collect::<Vec<_>>()
-			.join(" -> ")
-	);
-
-	actual_costs_total = round_to_currency(actual_costs_total);
-	savings_total = round_to_currency(savings_total);
-	println!("EXPECTED=${}", round_to_currency(expected_costs_total));
-	println!("ACTUAL=${}", actual_costs_total);
-	println!(
-		"By paying an extra ${}, you saved ${}",
-		original_extra_amount, savings_total
-	);
-	println!("Is debt snowball {}", is_debt_snowball);
-	println!("Total periods={}", count);
-
-	log::info!(
-		"Pay loans with ordering {:?}, total amount {}, savings {}",
-		ordering,
-		actual_costs_total,
-		savings_total
-	);
-
-	Ok((is_debt_snowball, actual_costs_total, savings_total))
 }
 
 impl fmt::Display for Loan {
@@ -291,19 +1009,90 @@ impl fmt::Display for Loan {
 			f,
 			"{}, P={}, r={}, n={}, A={}",
 			self.name, self.initial_value, self.rate, self.number_of_payments, self.payment_amount
-		)
+		)?;
+		if self.compounding != Compounding::Monthly
+			|| self.payment_frequency != PaymentFrequency::Monthly
+		{
+			write!(
+				f,
+				", compounding {}, paid {}",
+				self.compounding, self.payment_frequency
+			)?;
+		}
+		if let Some(schedule) = &self.rate_schedule {
+			write!(f, ", variable rate with {} changes", schedule.changes.len())?;
+		}
+		if let Some(promotion) = &self.promotion {
+			write!(
+				f,
+				", r={} for {} payments",
+				promotion.rate, promotion.payments
+			)?;
+			if promotion.deferred_interest {
+				write!(f, " with deferred interest")?;
+			}
+		}
+		match &self.kind {
+			LoanKind::Installment => {}
+			LoanKind::Revolving(minimum) => write!(f, ", revolving with minimum {}", minimum)?,
+			LoanKind::InterestOnly { payments } => {
+				write!(f, ", interest only for {} payments", payments)?
+			}
+			LoanKind::Balloon {
+				amortization_payments,
+			} => write!(
+				f,
+				", balloon with payments for {} payments",
+				amortization_payments
+			)?,
+			LoanKind::Student(student) => write!(
+				f,
+				", {} student loan repaid from month {}",
+				student.accrual, student.repayment_start
+			)?,
+		}
+		if let Some(mortgage) = &self.mortgage {
+			write!(
+				f,
+				", statement payment {} with PMI {} and escrow {}",
+				self.statement_payment(),
+				mortgage.pmi_for(self.initial_value),
+				mortgage.escrow
+			)?;
+		}
+		if let Some(prepayment) = &self.prepayment {
+			if let Some(penalty) = &prepayment.penalty {
+				match penalty.charge {
+					PenaltyCharge::PercentOfBalance(percent) => write!(
+						f,
+						", prepayment penalty {}% of the balance",
+						round_to_decimals(percent * 100.0, 3)
+					)?,
+					PenaltyCharge::MonthsOfInterest(months) => {
+						write!(f, ", prepayment penalty {} months of interest", months)?
+					}
+				}
+				if let Some(window_months) = penalty.window_months {
+					write!(f, " for {} months", window_months)?;
+				}
+			}
+			if let Some(cap) = prepayment.annual_cap {
+				write!(
+					f,
+					", prepayment capped at {}% a year",
+					round_to_decimals(cap * 100.0, 3)
+				)?;
+			}
+		}
+		if !self.fees.is_empty() {
+			write!(f, ", {} fees", self.fees.len())?;
+		}
+		Ok(())
 	}
 }
 
-fn within_five_cents(a: f64, b: f64) -> bool {
-	(a - b).abs() <= 0.05
-}
-
-fn approx_equal(a: f64, b: f64, decimal_places: u8) -> bool {
-	let factor = 10.0f64.powi(decimal_places as i32);
-	let a = (a * factor).round();
-	let b = (b * factor).round();
-	a == b
+pub(crate) fn within_five_cents(a: Money, b: Money) -> bool {
+	(a - b).abs() <= Money::from_cents(5)
 }
 
 pub fn round_to_currency(a: f64) -> f64 {
@@ -318,23 +1107,44 @@ pub fn round_to_decimals(a: f64, places: i32) -> f64 {
 pub enum Error {
 	LoanGoesToInf,
 	OtherError(String),
-	InvalidLoan(usize),
+	/// The loan at `index` has issues that have to be fixed before it can be paid off
+	InvalidLoan {
+		index: usize,
+		name: String,
+		issues: Vec<ValidationIssue>,
+	},
 }
 
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Error::LoanGoesToInf => write!(f, "the loans are never paid off"),
+			Error::OtherError(message) => write!(f, "{}", message),
+			Error::InvalidLoan { name, issues, .. } => {
+				write!(f, "loan '{}' is invalid", name)?;
+				for issue in issues.iter() {
+					write!(f, "; {}", issue)?;
+				}
+				Ok(())
+			}
+		}
+	}
+}
+
+impl std::error::Error for Error {}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
 	use test_case::test_case;
 
-	#[test_case(4.0, 4.0, 0 => true)]
-	#[test_case(4.001, 4.002, 0 => true)]
-	#[test_case(4.001, 4.002, 1 => true)]
-	#[test_case(4.001, 4.002, 2 => true)]
-	#[test_case(4.001, 4.002, 3 => false)]
-	#[test_case(4.001, 4.002, 4 => false)]
-	#[test_case(241.79, 241.789, 2 => true)]
-	fn approx_equal(a: f64, b: f64, decimal_places: u8) -> bool {
-		super::approx_equal(a, b, decimal_places)
+	#[test_case(24179, 24179 => true)]
+	#[test_case(24179, 24184 => true)]
+	#[test_case(24184, 24179 => true)]
+	#[test_case(24179, 24185 => false)]
+	#[test_case(24185, 24179 => false)]
+	fn within_five_cents(a: i64, b: i64) -> bool {
+		super::within_five_cents(Money::from_cents(a), Money::from_cents(b))
 	}
 
 	#[test_case(4.001, 0 => 4.0)]
@@ -358,14 +1168,16 @@ mod tests {
 	#[test_case(12000.00, 0.02083, 36, 477.12)] // 25% annual
 	fn calculate_payment_amount(i: f64, r: f64, n: i64, expected: f64) {
 		let loan = Loan {
-			initial_value: i,
+			initial_value: Money::from_f64(i),
 			rate: r,
 			number_of_payments: n,
-			payment_amount: 0.0, // value doesn't matter
-			name: "".to_owned(), // value doesn't matter
+			payment_amount: Money::ZERO, // value doesn't matter
+			name: "".to_owned(),         // value doesn't matter
+			..Default::default()
 		};
 
-		let calculated = super::round_to_currency(loan.calculate_payment_amount());
+		let calculated = Money::from_f64(loan.calculate_payment_amount());
+		let expected = Money::from_f64(expected);
 		assert!(
 			super::within_five_cents(expected, calculated),
 			"Expected {} to be within 5 cents of {}",
@@ -373,4 +1185,295 @@ mod tests {
 			expected
 		);
 	}
+
+	#[test_case(Compounding::Monthly, 12 => 0.005)]
+	#[test_case(Compounding::Annual, 12 => 0.00486755)]
+	#[test_case(Compounding::Daily, 12 => 0.00501211)]
+	#[test_case(Compounding::Monthly, 26 => 0.00230459)]
+	#[test_case(Compounding::Annual, 1 => 0.06)]
+	fn rate_per(compounding: Compounding, periods_per_year: i64) -> f64 {
+		let loan = Loan {
+			rate: 0.005, // 6% annual
+			compounding,
+			..Default::default()
+		};
+		super::round_to_decimals(loan.rate_per(periods_per_year), 8)
+	}
+
+	#[test]
+	fn biweekly_payment_amount() {
+		let loan = Loan {
+			initial_value: Money::from_f64(10000.0),
+			rate: 0.005,
+			number_of_payments: 130,
+			compounding: Compounding::Daily,
+			payment_frequency: PaymentFrequency::Biweekly,
+			..Default::default()
+		};
+		assert_eq!(
+			Money::from_f64(loan.calculate_payment_amount()),
+			Money::from_f64(89.14)
+		);
+	}
+
+	#[test_case(1 => (0.0, 0.0))]
+	#[test_case(12 => (0.0, 0.0))]
+	#[test_case(13 => (0.02, 0.02))]
+	fn promotional_rate(payment: i64) -> (f64, f64) {
+		let loan = Loan {
+			rate: 0.02,
+			promotion: Some(Promotion {
+				payments: 12,
+				rate: 0.0,
+				deferred_interest: true,
+			}),
+			..Default::default()
+		};
+		assert_eq!(loan.standard_rate_for_payment(payment), 0.02);
+		(
+			loan.rate_for_payment(payment),
+			loan.lowest_rate_from(payment),
+		)
+	}
+
+	fn record(rate: Option<f64>, apr: Option<f64>, apy: Option<f64>) -> LoanRecord {
+		LoanRecord {
+			name: "l1".to_owned(),
+			initial_value: Some(Money::from_f64(10000.0)),
+			rate,
+			apr,
+			apy,
+			number_of_payments: Some(48),
+			payment_amount: Some(Money::from_f64(241.79)),
+			compounding: Compounding::Monthly,
+			payment_frequency: PaymentFrequency::Monthly,
+			promo_payments: None,
+			promo_apr: None,
+			deferred_interest: None,
+			minimum_percent: None,
+			minimum_plus_interest: None,
+			minimum_floor: None,
+			interest_only_payments: None,
+			balloon_amortization_payments: None,
+			repayment_start: None,
+			accrual: None,
+			capitalization: None,
+			home_value: None,
+			pmi: None,
+			pmi_removal_ltv: None,
+			escrow: None,
+			penalty_percent: None,
+			penalty_months_interest: None,
+			penalty_window_months: None,
+			annual_prepayment_cap: None,
+			annual_fee: None,
+			annual_fee_month: None,
+			monthly_fee: None,
+			late_fee: None,
+			late_fee_months: None,
+		}
+	}
+
+	#[test_case(Some(0.00625), None, None => Some(0.00625))]
+	#[test_case(None, Some(0.075), None => Some(0.00625))]
+	#[test_case(None, None, Some(0.0776326) => Some(0.00625))]
+	#[test_case(None, None, None => Some(0.00625018))] // worked out from the payment, rounded to the cent
+	#[test_case(Some(0.00625), Some(0.075), None => None)]
+	fn loan_record(rate: Option<f64>, apr: Option<f64>, apy: Option<f64>) -> Option<f64> {
+		Loan::try_from(record(rate, apr, apy))
+			.ok()
+			.map(|l| super::round_to_decimals(l.rate, 8))
+	}
+
+	#[test]
+	fn revolving_loan_record() {
+		let revolving = LoanRecord {
+			number_of_payments: None,
+			payment_amount: None,
+			minimum_percent: Some(0.02),
+			minimum_floor: Some(Money::from_f64(35.0)),
+			..record(Some(0.015), None, None)
+		};
+		let loan = Loan::try_from(revolving).unwrap();
+		assert_eq!(loan.number_of_payments, 0);
+		// 2% of the balance plus a month of interest
+		assert_eq!(loan.payment_amount, Money::from_f64(350.0));
+		assert!(!loan.has_level_payments());
+
+		let missing_terms = LoanRecord {
+			number_of_payments: None,
+			payment_amount: None,
+			..record(Some(0.015), None, None)
+		};
+		assert!(Loan::try_from(missing_terms).is_err());
+	}
+
+	#[test_case(Some(24), None => Some(LoanKind::InterestOnly { payments: 24 }))]
+	#[test_case(None, Some(360) => Some(LoanKind::Balloon { amortization_payments: 360 }))]
+	#[test_case(None, None => Some(LoanKind::Installment))]
+	#[test_case(Some(24), Some(360) => None)]
+	fn loan_record_kind(
+		interest_only_payments: Option<i64>,
+		balloon_amortization_payments: Option<i64>,
+	) -> Option<LoanKind> {
+		let record = LoanRecord {
+			interest_only_payments,
+			balloon_amortization_payments,
+			..record(Some(0.00625), None, None)
+		};
+		Loan::try_from(record).ok().map(|l| l.kind)
+	}
+
+	#[test]
+	fn student_loan_record() {
+		let student = LoanRecord {
+			payment_amount: None,
+			repayment_start: Some(7),
+			capitalization: Some("4; 6".to_owned()),
+			..record(Some(0.005), None, None)
+		};
+		let loan = Loan::try_from(student).unwrap();
+		let expected = StudentLoan {
+			repayment_start: 7,
+			accrual: Accrual::Unsubsidized,
+			capitalization: vec![4, 6],
+		};
+		assert!(loan.kind == LoanKind::Student(expected));
+		assert!(loan.payment_amount > Money::from_f64(241.79));
+
+		let bad_month = LoanRecord {
+			repayment_start: Some(7),
+			capitalization: Some("four".to_owned()),
+			..record(Some(0.005), None, None)
+		};
+		assert!(Loan::try_from(bad_month).is_err());
+	}
+
+	#[test]
+	fn fees_loan_record() {
+		let with_fees = LoanRecord {
+			annual_fee: Some(Money::from_f64(95.0)),
+			monthly_fee: Some(Money::from_f64(5.0)),
+			late_fee: Some(Money::from_f64(39.0)),
+			late_fee_months: Some("3;9".to_owned()),
+			..record(Some(0.00625), None, None)
+		};
+		let loan = Loan::try_from(with_fees).unwrap();
+		let frequencies: Vec<FeeFrequency> = loan.fees.into_iter().map(|f| f.frequency).collect();
+		assert_eq!(
+			frequencies,
+			vec![
+				FeeFrequency::Annual { month: 1 },
+				FeeFrequency::Monthly,
+				FeeFrequency::Months(vec![3, 9])
+			]
+		);
+
+		// a credit card's annual fee and prepayment columns count as much as any other loan's
+		let card = LoanRecord {
+			number_of_payments: None,
+			payment_amount: None,
+			minimum_percent: Some(0.02),
+			annual_fee: Some(Money::from_f64(95.0)),
+			annual_fee_month: Some(6),
+			annual_prepayment_cap: Some(0.5),
+			..record(Some(0.015), None, None)
+		};
+		let loan = Loan::try_from(card).unwrap();
+		assert!(matches!(loan.kind, LoanKind::Revolving(_)));
+		assert_eq!(
+			loan.fees,
+			vec![Fee {
+				amount: Money::from_f64(95.0),
+				frequency: FeeFrequency::Annual { month: 6 },
+			}]
+		);
+		assert_eq!(loan.prepayment.and_then(|p| p.annual_cap), Some(0.5));
+	}
+
+	#[test_case(LoanTerm::InitialValue, 24179 => Ok((1000004, 0.00625, 48, 24179)))]
+	#[test_case(LoanTerm::Rate, 24179 => Ok((1000000, 0.00625, 48, 24179)))]
+	#[test_case(LoanTerm::NumberOfPayments, 24179 => Ok((1000000, 0.00625, 48, 24179)))]
+	#[test_case(LoanTerm::NumberOfPayments, 25000 => Ok((1000000, 0.00625, 47, 25000)))]
+	#[test_case(LoanTerm::PaymentAmount, 0 => Ok((1000000, 0.00625, 48, 24179)))]
+	#[test_case(LoanTerm::NumberOfPayments, 5000 => Err(()))] // doesn't cover the interest
+	#[test_case(LoanTerm::Rate, 20000 => Err(()))] // pays back less than was borrowed
+	fn solve_for(term: LoanTerm, payment_amount: i64) -> Result<(i64, f64, i64, i64), ()> {
+		let mut loan = Loan::new(
+			"l1".to_owned(),
+			Money::from_cents(1000000),
+			0.00625,
+			48,
+			Money::from_cents(payment_amount),
+		);
+		match term {
+			LoanTerm::InitialValue => loan.initial_value = Money::ZERO,
+			LoanTerm::Rate => loan.rate = 0.0,
+			LoanTerm::NumberOfPayments => loan.number_of_payments = 0,
+			LoanTerm::PaymentAmount => {}
+		}
+		loan.solve_for(term).map_err(|_| ())?;
+		Ok((
+			loan.initial_value.cents(),
+			super::round_to_decimals(loan.rate, 6),
+			loan.number_of_payments,
+			loan.payment_amount.cents(),
+		))
+	}
+
+	#[test]
+	fn from_terms() {
+		let loan = Loan::from_terms(
+			"l1".to_owned(),
+			Some(Money::from_f64(10000.0)),
+			Some(0.00625),
+			None,
+			Some(Money::from_f64(241.79)),
+		)
+		.unwrap();
+		assert_eq!(loan.number_of_payments, 48);
+		assert!(Loan::from_terms(
+			"l1".to_owned(),
+			Some(Money::from_f64(10000.0)),
+			None,
+			None,
+			Some(Money::from_f64(241.79)),
+		)
+		.is_err());
+	}
+
+	#[test_case(LoanKind::Installment => 48)]
+	#[test_case(LoanKind::InterestOnly { payments: 12 } => 36)]
+	#[test_case(LoanKind::Balloon { amortization_payments: 360 } => 360)]
+	fn amortization_payments(kind: LoanKind) -> i64 {
+		let loan = Loan {
+			number_of_payments: 48,
+			kind,
+			..Default::default()
+		};
+		loan.amortization_payments()
+	}
+
+	#[test]
+	fn set_rate() {
+		let mut loan = Loan::default();
+		loan.set_rate(Rate::from_apy(0.0776326, Compounding::Monthly));
+		assert_eq!(super::round_to_decimals(loan.rate, 8), 0.00625);
+		assert_eq!(super::round_to_decimals(loan.annual_rate().apr(), 8), 0.075);
+	}
+
+	#[test_case(100000, 24179 => (24179, 76446))]
+	#[test_case(10000, 24179 => (10063, 0))]
+	#[test_case(10000, 0 => (0, 10063))]
+	fn pay_loan(present_value: i64, payment_amount: i64) -> (i64, i64) {
+		let loan = Loan {
+			rate: 0.00625,
+			..Default::default()
+		};
+		let (paid, remaining) = loan.pay_loan(
+			Money::from_cents(present_value),
+			Money::from_cents(payment_amount),
+		);
+		(paid.cents(), remaining.cents())
+	}
 }
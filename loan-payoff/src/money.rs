@@ -0,0 +1,262 @@
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+/// An amount of money held as a whole number of cents
+///
+/// All balances and payments are tracked in this type so that repeated arithmetic never drifts away
+/// from what a statement would show. Rates stay `f64`, multiplying by one rounds back to the cent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(i64);
+
+impl Money {
+	pub const ZERO: Money = Money(0);
+
+	pub const fn from_cents(cents: i64) -> Money {
+		Money(cents)
+	}
+
+	/// Rounds to the nearest cent, NaN becomes zero
+	pub fn from_f64(amount: f64) -> Money {
+		Money((amount * 100.0).round() as i64)
+	}
+
+	pub fn cents(self) -> i64 {
+		self.0
+	}
+
+	pub fn to_f64(self) -> f64 {
+		self.0 as f64 / 100.0
+	}
+
+	/// Applies a rate to this amount, rounding the result to the nearest cent
+	pub fn mul_rate(self, rate: f64) -> Money {
+		Money((self.0 as f64 * rate).round() as i64)
+	}
+
+	pub fn abs(self) -> Money {
+		Money(self.0.abs())
+	}
+
+	pub fn is_zero(self) -> bool {
+		self.0 == 0
+	}
+
+	pub fn is_positive(self) -> bool {
+		self.0 > 0
+	}
+
+	pub fn is_negative(self) -> bool {
+		self.0 < 0
+	}
+}
+
+impl Add for Money {
+	type Output = Money;
+
+	fn add(self, other: Money) -> Money {
+		Money(self.0 + other.0)
+	}
+}
+
+impl AddAssign for Money {
+	fn add_assign(&mut self, other: Money) {
+		self.0 += other.0;
+	}
+}
+
+impl Sub for Money {
+	type Output = Money;
+
+	fn sub(self, other: Money) -> Money {
+		Money(self.0 - other.0)
+	}
+}
+
+impl SubAssign for Money {
+	fn sub_assign(&mut self, other: Money) {
+		self.0 -= other.0;
+	}
+}
+
+impl Neg for Money {
+	type Output = Money;
+
+	fn neg(self) -> Money {
+		Money(-self.0)
+	}
+}
+
+impl Mul<i64> for Money {
+	type Output = Money;
+
+	fn mul(self, count: i64) -> Money {
+		Money(self.0 * count)
+	}
+}
+
+impl Sum for Money {
+	fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+		iter.fold(Money::ZERO, Add::add)
+	}
+}
+
+impl<'a> Sum<&'a Money> for Money {
+	fn sum<I: Iterator<Item = &'a Money>>(iter: I) -> Money {
+		iter.copied().sum()
+	}
+}
+
+impl fmt::Display for Money {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let sign = if self.0 < 0 { "-" } else { "" };
+		let cents = self.0.unsigned_abs();
+		// pad so callers can line amounts up in columns
+		f.pad(&format!("{}{}.{:02}", sign, cents / 100, cents % 100))
+	}
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseMoneyError(String);
+
+impl fmt::Display for ParseMoneyError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "'{}' is not a valid amount of money", self.0)
+	}
+}
+
+impl std::error::Error for ParseMoneyError {}
+
+impl FromStr for Money {
+	type Err = ParseMoneyError;
+
+	/// Parses a plain decimal amount like `-1234.5`, rounding anything past the cents
+	fn from_str(s: &str) -> Result<Money, ParseMoneyError> {
+		let err = || ParseMoneyError(s.to_owned());
+		let trimmed = s.trim();
+		let (negative, digits) = match trimmed.strip_prefix('-') {
+			Some(rest) => (true, rest),
+			None => (false, trimmed),
+		};
+		let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+		if (whole.is_empty() && fraction.is_empty())
+			|| !whole.chars().all(|c| c.is_ascii_digit())
+			|| !fraction.chars().all(|c| c.is_ascii_digit())
+		{
+			return Err(err());
+		}
+
+		let whole: i64 = if whole.is_empty() {
+			0
+		} else {
+			whole.parse().map_err(|_| err())?
+		};
+		let mut fraction_digits = fraction.bytes().map(|b| (b - b'0') as i64);
+		let tenths = fraction_digits.next().unwrap_or(0);
+		let hundredths = fraction_digits.next().unwrap_or(0);
+		let round_up = fraction_digits.next().unwrap_or(0) >= 5;
+
+		let cents = whole
+			.checked_mul(100)
+			.and_then(|c| c.checked_add(tenths * 10 + hundredths + round_up as i64))
+			.ok_or_else(err)?;
+		Ok(Money(if negative { -cents } else { cents }))
+	}
+}
+
+impl Serialize for Money {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_f64(self.to_f64())
+	}
+}
+
+struct MoneyVisitor;
+
+impl Visitor<'_> for MoneyVisitor {
+	type Value = Money;
+
+	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str("an amount of money")
+	}
+
+	fn visit_i64<E: de::Error>(self, v: i64) -> Result<Money, E> {
+		v.checked_mul(100)
+			.map(Money)
+			.ok_or_else(|| E::custom(format!("{} is too large", v)))
+	}
+
+	fn visit_u64<E: de::Error>(self, v: u64) -> Result<Money, E> {
+		i64::try_from(v)
+			.map_err(|_| E::custom(format!("{} is too large", v)))
+			.and_then(|v| self.visit_i64(v))
+	}
+
+	fn visit_f64<E: de::Error>(self, v: f64) -> Result<Money, E> {
+		if v.is_finite() {
+			Ok(Money::from_f64(v))
+		} else {
+			Err(E::custom(format!("{} is not a valid amount of money", v)))
+		}
+	}
+
+	fn visit_str<E: de::Error>(self, v: &str) -> Result<Money, E> {
+		v.parse().map_err(E::custom)
+	}
+}
+
+impl<'de> Deserialize<'de> for Money {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Money, D::Error> {
+		deserializer.deserialize_any(MoneyVisitor)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use test_case::test_case;
+
+	#[test_case("10000.00" => Ok(Money(1000000)))]
+	#[test_case("241.79" => Ok(Money(24179)))]
+	#[test_case("12.5" => Ok(Money(1250)))]
+	#[test_case(".5" => Ok(Money(50)))]
+	#[test_case("7" => Ok(Money(700)))]
+	#[test_case("-3.10" => Ok(Money(-310)))]
+	#[test_case("4.099" => Ok(Money(410)))]
+	#[test_case("4.094" => Ok(Money(409)))]
+	#[test_case("" => Err(ParseMoneyError("".to_owned())))]
+	#[test_case("1.2.3" => Err(ParseMoneyError("1.2.3".to_owned())))]
+	#[test_case("abc" => Err(ParseMoneyError("abc".to_owned())))]
+	fn from_str(s: &str) -> Result<Money, ParseMoneyError> {
+		s.parse()
+	}
+
+	#[test_case(Money(2824490) => "28244.90")]
+	#[test_case(Money(5) => "0.05")]
+	#[test_case(Money(-310) => "-3.10")]
+	#[test_case(Money::ZERO => "0.00")]
+	fn display(m: Money) -> String {
+		m.to_string()
+	}
+
+	#[test]
+	fn display_width() {
+		assert_eq!(format!("{:>8}", Money(-310)), "   -3.10");
+	}
+
+	#[test_case(Money(1000000), 0.00625 => Money(6250))]
+	#[test_case(Money(972071), 0.00625 => Money(6075))]
+	#[test_case(Money(1200000), 0.02083 => Money(24996))]
+	fn mul_rate(m: Money, rate: f64) -> Money {
+		m.mul_rate(rate)
+	}
+
+	#[test_case(241.79 => Money(24179))]
+	#[test_case(4.001 => Money(400))]
+	#[test_case(f64::NAN => Money::ZERO)]
+	fn from_f64(a: f64) -> Money {
+		Money::from_f64(a)
+	}
+}
@@ -0,0 +1,55 @@
+use crate::Money;
+
+/// The parts of a mortgage payment besides principal and interest
+///
+/// Private mortgage insurance (PMI) is charged with every payment until the balance is down to `pmi_removal_ltv`
+/// of the home value, so paying the loan down faster drops it sooner and that counts as savings. Escrow pays the
+/// property taxes and home insurance, which are owed whether or not the loan is paid off, so it is passed through
+/// without counting as a cost.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Mortgage {
+	/// Value of the home when the loan was taken out, what the loan-to-value ratio is worked out against
+	pub home_value: Money,
+	/// PMI added to each payment, zero for none
+	pub pmi: Money,
+	/// Loan-to-value ratio PMI stops at, 0.8 for 80%
+	pub pmi_removal_ltv: f64,
+	/// Taxes and insurance collected with each payment
+	pub escrow: Money,
+}
+
+impl Mortgage {
+	/// Whether a payment on a balance is charged PMI
+	pub fn charges_pmi(&self, balance: Money) -> bool {
+		self.pmi.is_positive() && balance > self.home_value.mul_rate(self.pmi_removal_ltv)
+	}
+
+	/// PMI charged with a payment on a balance
+	pub fn pmi_for(&self, balance: Money) -> Money {
+		if self.charges_pmi(balance) {
+			self.pmi
+		} else {
+			Money::ZERO
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use test_case::test_case;
+
+	#[test_case(20000000 => 10000)]
+	#[test_case(16000100 => 10000)]
+	#[test_case(16000000 => 0)]
+	#[test_case(0 => 0)]
+	fn pmi_for(balance: i64) -> i64 {
+		let mortgage = Mortgage {
+			home_value: Money::from_cents(20000000),
+			pmi: Money::from_cents(10000),
+			pmi_removal_ltv: 0.8,
+			escrow: Money::from_cents(35000),
+		};
+		mortgage.pmi_for(Money::from_cents(balance)).cents()
+	}
+}
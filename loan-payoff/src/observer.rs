@@ -0,0 +1,21 @@
+use crate::{Error, Loan, PayoffResult};
+
+/// Receives progress reports from the payoff simulation
+///
+/// Every method has an empty default, so implementations only override the events they care about.
+/// The library never writes to stdout itself, callers that want output install an observer.
+pub trait PayoffObserver {
+	/// Called when a loan reaches a zero balance
+	fn loan_paid_off(&mut self, _loan: &Loan, _period: i64) {}
+
+	/// Called once an ordering has been fully paid off
+	fn ordering_paid_off(&mut self, _loans: &[&Loan], _result: &PayoffResult) {}
+
+	/// Called when an ordering could not be paid off
+	fn ordering_failed(&mut self, _loans: &[&Loan], _ordering: &[usize], _error: &Error) {}
+}
+
+/// Observer that ignores everything, used when the caller doesn't supply one
+pub struct SilentObserver;
+
+impl PayoffObserver for SilentObserver {}
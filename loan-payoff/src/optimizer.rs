@@ -0,0 +1,318 @@
+use crate::simulation::{self, Simulation};
+use crate::{
+	observe_payoff, Allocation, Error, ExtraPayments, FixedOrder, Loan, Money, OptimalPayoff,
+	PayoffObserver, PayoffResult, PayoffStrategy, PromotionDeadlines, SilentObserver, Split,
+};
+
+/// Finds the ordering with the most savings without trying every ordering
+///
+/// Gives the same savings as `pay_loans_all_orderings`, but works through the orderings one loan at a time
+/// and skips every ordering that starts with a prefix that can't beat the best ordering found so far.
+/// When several orderings tie the one returned may differ from `pay_loans_all_orderings`.
+///
+/// When a loan has a deferred-interest promotion every ordering is also tried with `PromotionDeadlines`, which
+/// clears that balance just before the promotion ends and can beat every ordering on its own.
+pub fn optimal_payoff(
+	loans: &[&Loan],
+	extra_payments: impl Into<ExtraPayments>,
+) -> Result<OptimalPayoff, Error> {
+	optimal_payoff_with_observer(loans, extra_payments, &mut SilentObserver)
+}
+
+/// Same as `optimal_payoff`, reporting each ordering that improves on the best so far to the observer
+pub fn optimal_payoff_with_observer(
+	loans: &[&Loan],
+	extra_payments: impl Into<ExtraPayments>,
+	observer: &mut dyn PayoffObserver,
+) -> Result<OptimalPayoff, Error> {
+	optimal_payoff_with_allocations(loans, extra_payments, &[Allocation::default()], observer)
+}
+
+/// Same as `optimal_payoff_with_observer`, also picking the best of the allocations given
+///
+/// Every ordering is tried with every allocation, ties go to the allocation that comes first. Each allocation
+/// has to be valid for the loans given. `Allocation::percentage_splits` makes a list that covers every split in
+/// steps of a given size. The debt snowball result is always for the whole extra following the ordering.
+pub fn optimal_payoff_with_allocations(
+	loans: &[&Loan],
+	extra_payments: impl Into<ExtraPayments>,
+	allocations: &[Allocation],
+	observer: &mut dyn PayoffObserver,
+) -> Result<OptimalPayoff, Error> {
+	let extra_payments = extra_payments.into();
+	let ordering: Vec<usize> = (0..loans.len()).collect();
+	simulation::validate_loans(loans, &ordering)?;
+
+	let has_deadlines = loans
+		.iter()
+		.any(|l| l.promotion.as_ref().is_some_and(|p| p.deferred_interest));
+	let mut search = Search::new(loans, &extra_payments, any_loan);
+	for allocation in allocations.iter() {
+		allocation
+			.validate(loans.len())
+			.map_err(Error::OtherError)?;
+		search.run(allocation, false, observer)?;
+		if has_deadlines {
+			search.run(allocation, true, observer)?;
+		}
+	}
+	let best = search.best.ok_or(Error::LoanGoesToInf)?;
+	// Note: there can be multiple debt snowball orderings (2 loans with same amount)
+	let mut search = Search::new(loans, &extra_payments, smallest_initial_value);
+	search.run(&NO_ALLOCATION, false, &mut SilentObserver)?;
+	let debt_snowball = search.best;
+	let savings_over_debt_snowball = debt_snowball
+		.as_ref()
+		.map_or(Money::ZERO, |s| best.savings_total - s.savings_total);
+	Ok(OptimalPayoff {
+		best,
+		debt_snowball,
+		savings_over_debt_snowball,
+	})
+}
+
+static NO_ALLOCATION: Allocation = Allocation { rules: Vec::new() };
+
+/// Picks which of the remaining loans may come next in the ordering
+type Candidates = fn(&[&Loan], &[usize]) -> Vec<usize>;
+
+fn any_loan(_loans: &[&Loan], remaining: &[usize]) -> Vec<usize> {
+	remaining.to_vec()
+}
+
+fn smallest_initial_value(loans: &[&Loan], remaining: &[usize]) -> Vec<usize> {
+	let smallest = remaining.iter().map(|&i| loans[i].initial_value).min();
+	remaining
+		.iter()
+		.copied()
+		.filter(|&i| Some(loans[i].initial_value) == smallest)
+		.collect()
+}
+
+/// Depth first search over ordering prefixes
+///
+/// Each node keeps the simulation run forward for as long as the payments only depend on its prefix. That is
+/// until the last unpaid loan of the prefix is paid off, since what it doesn't use moves on to the rest of the
+/// ordering, until a loan outside the prefix is paid off by its regular payments, or until a prepayment cap
+/// holds money back for the rest of the ordering. Every ordering under the
+/// node shares that simulation, so the node can be pruned using a lower bound on the rest. The bound holds for
+/// any allocation, so the best found for one allocation prunes the search for the next.
+struct Search<'a> {
+	loans: &'a [&'a Loan],
+	extra_payments: &'a ExtraPayments,
+	allocation: &'a Allocation,
+	promotion_deadlines: bool,
+	candidates: Candidates,
+	max_number_payments: i64,
+	best: Option<PayoffResult>,
+}
+
+impl<'a> Search<'a> {
+	fn new(
+		loans: &'a [&'a Loan],
+		extra_payments: &'a ExtraPayments,
+		candidates: Candidates,
+	) -> Search<'a> {
+		Search {
+			loans,
+			extra_payments,
+			allocation: &NO_ALLOCATION,
+			promotion_deadlines: false,
+			candidates,
+			max_number_payments: simulation::max_number_payments(loans),
+			best: None,
+		}
+	}
+
+	fn run(
+		&mut self,
+		allocation: &'a Allocation,
+		promotion_deadlines: bool,
+		observer: &mut dyn PayoffObserver,
+	) -> Result<(), Error> {
+		self.allocation = allocation;
+		self.promotion_deadlines = promotion_deadlines;
+		let mut root = Simulation::new(self.loans, self.extra_payments);
+		root.promotion_deadlines = promotion_deadlines;
+		self.visit(&mut Vec::new(), &root, observer)
+	}
+
+	fn visit(
+		&mut self,
+		prefix: &mut Vec<usize>,
+		node: &Simulation<'a>,
+		observer: &mut dyn PayoffObserver,
+	) -> Result<(), Error> {
+		if prefix.len() == self.loans.len() {
+			return self.evaluate(prefix, node.clone(), observer);
+		}
+		if let Some(best) = &self.best {
+			if self.lower_bound(node) > best.actual_costs_total {
+				return Ok(());
+			}
+		}
+
+		let remaining: Vec<usize> = (0..self.loans.len())
+			.filter(|i| !prefix.contains(i))
+			.collect();
+		let mut candidates = (self.candidates)(self.loans, &remaining);
+		// highest rate first, so the first ordering tried is the debt avalanche and sets a good bound early
+		candidates.sort_by(|&a, &b| self.loans[b].rate.total_cmp(&self.loans[a].rate));
+		for next in candidates {
+			prefix.push(next);
+			let child = self.advance(node, prefix);
+			self.visit(prefix, &child, observer)?;
+			prefix.pop();
+		}
+		Ok(())
+	}
+
+	/// Runs the parent's simulation forward for as long as the prefix decides the payments
+	fn advance(&self, parent: &Simulation<'a>, prefix: &[usize]) -> Simulation<'a> {
+		let ordering = self.complete_ordering(prefix);
+		let mut sim = parent.clone();
+		while sim.count < self.max_number_payments {
+			let last_unpaid = match prefix.iter().rev().find(|&&i| !sim.is_paid_off(i)) {
+				Some(&i) => i,
+				None => break,
+			};
+			let mut next = sim.clone();
+			next.step(&ordering, self.allocation, None, &mut SilentObserver);
+			// money held back by a prepayment cap may have gone to loans the prefix doesn't order yet
+			if next.capped
+				|| next.is_paid_off(last_unpaid)
+				|| ordering[prefix.len()..]
+					.iter()
+					.any(|&i| !sim.is_paid_off(i) && next.is_paid_off(i))
+			{
+				break;
+			}
+			sim = next;
+		}
+		sim
+	}
+
+	fn evaluate(
+		&mut self,
+		ordering: &[usize],
+		mut sim: Simulation<'a>,
+		observer: &mut dyn PayoffObserver,
+	) -> Result<(), Error> {
+		while !sim.is_done() {
+			if sim.count >= self.max_number_payments {
+				return Ok(());
+			}
+			sim.step(ordering, self.allocation, None, &mut SilentObserver);
+		}
+		let cost: Money = sim.actual_costs.iter().sum();
+
+		if self
+			.best
+			.as_ref()
+			.is_none_or(|b| cost < b.actual_costs_total)
+		{
+			let mut strategy: Box<dyn PayoffStrategy> = Box::new(Split {
+				allocation: self.allocation.clone(),
+				then: Box::new(FixedOrder(ordering.to_vec())),
+			});
+			if self.promotion_deadlines {
+				strategy = Box::new(PromotionDeadlines(strategy));
+			}
+			self.best = Some(observe_payoff(
+				self.loans,
+				self.extra_payments,
+				strategy.as_ref(),
+				observer,
+			)?);
+		}
+		Ok(())
+	}
+
+	/// Lowest total cost any ordering under the node could have
+	///
+	/// Whatever the ordering, each period every unpaid loan gets at most its regular payment plus whatever
+	/// is left of the extra payment, and the extra payment grows by the regular payment of every loan paid off. The
+	/// least interest that can cost is when the extra always goes to the highest rate first. That is worked out
+	/// without rounding, so a cent per loan per period is taken off to cover the rounding the real simulation does.
+	///
+	/// Variable-rate loans are counted at the lowest rate they have left. When a rate change is still going to
+	/// reamortize a payment there is no telling how much money is going around, so only the balances are counted.
+	/// A last payment that is trued up pays off whatever is left of its loan.
+	fn lower_bound(&self, sim: &Simulation) -> Money {
+		let mut unpaid: Vec<usize> = (0..self.loans.len())
+			.filter(|&i| !sim.is_paid_off(i))
+			.collect();
+		let costs_so_far: Money = sim.actual_costs.iter().sum();
+		let remaining: Money = unpaid.iter().map(|&i| sim.remaining_amounts[i]).sum();
+		let periods_left = (self.max_number_payments - sim.count).max(0);
+		let rounding = Money::from_cents(unpaid.len() as i64 * periods_left);
+		if unpaid.iter().any(|&i| sim.payment_may_change(i)) {
+			return costs_so_far + remaining - rounding;
+		}
+
+		let rates: Vec<f64> = (0..self.loans.len())
+			.map(|i| sim.lowest_rate_ahead(i))
+			.collect();
+		unpaid.sort_by(|&a, &b| rates[b].total_cmp(&rates[a]));
+		let mut balances: Vec<f64> = unpaid
+			.iter()
+			.map(|&i| sim.remaining_amounts[i].to_f64())
+			.collect();
+
+		let mut interest = 0.0;
+		for period in sim.count + 1..=self.max_number_payments {
+			if balances.iter().all(|&b| b <= 0.0) {
+				break;
+			}
+			let regular_payments: Vec<f64> = unpaid
+				.iter()
+				.map(|&i| {
+					if sim.is_payment_due(i, period) {
+						sim.payment_amounts[i].to_f64()
+					} else {
+						0.0
+					}
+				})
+				.collect();
+			let trued_up: Vec<bool> = unpaid
+				.iter()
+				.map(|&i| sim.is_payment_due(i, period) && sim.is_trued_up(i, period))
+				.collect();
+			let mut extra_left = (sim.extra_for_period(period) + sim.lump_sum_for_period(period))
+				.to_f64() + regular_payments.iter().sum::<f64>();
+			for (((balance, &i), regular_payment), trued_up) in balances
+				.iter_mut()
+				.zip(unpaid.iter())
+				.zip(regular_payments)
+				.zip(trued_up)
+			{
+				let period_interest = *balance * rates[i];
+				interest += period_interest;
+				*balance += period_interest;
+				if trued_up {
+					// the last payment pays off the balance with money of its own, none of it is freed up
+					*balance = 0.0;
+					extra_left -= regular_payment;
+					continue;
+				}
+				let payment = regular_payment.min(*balance);
+				*balance -= payment;
+				extra_left -= payment;
+			}
+			for balance in balances.iter_mut() {
+				let payment = extra_left.min(*balance);
+				*balance -= payment;
+				extra_left -= payment;
+			}
+		}
+
+		costs_so_far + remaining + Money::from_f64(interest) - rounding
+	}
+
+	/// The prefix followed by the other loans in index order
+	fn complete_ordering(&self, prefix: &[usize]) -> Vec<usize> {
+		let mut ordering = prefix.to_vec();
+		ordering.extend((0..self.loans.len()).filter(|i| !prefix.contains(i)));
+		ordering
+	}
+}
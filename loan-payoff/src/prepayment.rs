@@ -0,0 +1,71 @@
+use crate::Money;
+
+/// Limits on paying a loan ahead of schedule
+///
+/// A penalty is charged when the loan is paid off before its last scheduled payment, and counts as a cost of the
+/// loan. The cap limits the extra principal paid in each year of the loan, counted from the first month, and
+/// whatever it holds back goes on to the next loan in the ordering.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Prepayment {
+	pub penalty: Option<PrepaymentPenalty>,
+	/// Most extra principal a year, as a fraction of `Loan::initial_value`, 0.2 for 20%
+	pub annual_cap: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrepaymentPenalty {
+	pub charge: PenaltyCharge,
+	/// Number of months from the start of the loan the penalty applies in, `None` for all of it
+	pub window_months: Option<i64>,
+}
+
+/// How much an early payoff is charged
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PenaltyCharge {
+	/// Fraction of the balance paid off early, 0.02 for 2%
+	PercentOfBalance(f64),
+	/// Months of interest on the balance paid off early
+	MonthsOfInterest(i64),
+}
+
+impl Prepayment {
+	/// Penalty for paying off a balance early in a 1-based month, at a monthly rate
+	pub fn penalty_for(&self, balance: Money, month: i64, monthly_rate: f64) -> Money {
+		let penalty = match &self.penalty {
+			Some(penalty) if penalty.window_months.is_none_or(|w| month <= w) => penalty,
+			_ => return Money::ZERO,
+		};
+		match penalty.charge {
+			PenaltyCharge::PercentOfBalance(percent) => balance.mul_rate(percent),
+			PenaltyCharge::MonthsOfInterest(months) => balance.mul_rate(monthly_rate) * months,
+		}
+	}
+
+	/// Most extra principal a year on a loan of `initial_value`, `None` when there is no cap
+	pub fn annual_limit(&self, initial_value: Money) -> Option<Money> {
+		self.annual_cap.map(|cap| initial_value.mul_rate(cap))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use test_case::test_case;
+
+	#[test_case(PenaltyCharge::PercentOfBalance(0.02), None, 30 => 20000)]
+	#[test_case(PenaltyCharge::MonthsOfInterest(3), None, 30 => 15000)]
+	#[test_case(PenaltyCharge::MonthsOfInterest(3), Some(24), 24 => 15000)]
+	#[test_case(PenaltyCharge::MonthsOfInterest(3), Some(24), 25 => 0)]
+	fn penalty_for(charge: PenaltyCharge, window_months: Option<i64>, month: i64) -> i64 {
+		let prepayment = Prepayment {
+			penalty: Some(PrepaymentPenalty {
+				charge,
+				window_months,
+			}),
+			annual_cap: None,
+		};
+		prepayment
+			.penalty_for(Money::from_cents(1000000), month, 0.005)
+			.cents()
+	}
+}
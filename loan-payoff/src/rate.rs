@@ -0,0 +1,123 @@
+use crate::Compounding;
+use std::fmt;
+
+/// An interest rate, kept as the nominal annual rate (APR) and how often it compounds
+///
+/// The same APR costs more the more often it compounds, the APY is what it works out to over a whole year.
+/// Rates are fractions, 0.075 for 7.5%.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rate {
+	apr: f64,
+	compounding: Compounding,
+}
+
+impl Rate {
+	/// From the nominal annual rate, which is the rate per compounding period times the periods in a year
+	pub fn from_apr(apr: f64, compounding: Compounding) -> Rate {
+		Rate { apr, compounding }
+	}
+
+	/// From the effective annual rate (APY), what a balance grows by over a year of compounding
+	pub fn from_apy(apy: f64, compounding: Compounding) -> Rate {
+		let m = compounding.periods_per_year() as f64;
+		Rate {
+			apr: m * (f64::powf(1.0 + apy, 1.0 / m) - 1.0),
+			compounding,
+		}
+	}
+
+	/// From the effective rate over one of `periods_per_year` equal periods
+	pub fn from_periodic(rate: f64, periods_per_year: i64, compounding: Compounding) -> Rate {
+		Rate::from_apy(
+			f64::powf(1.0 + rate, periods_per_year as f64) - 1.0,
+			compounding,
+		)
+	}
+
+	pub fn apr(self) -> f64 {
+		self.apr
+	}
+
+	pub fn apy(self) -> f64 {
+		let m = self.compounding.periods_per_year() as f64;
+		f64::powf(1.0 + self.apr / m, m) - 1.0
+	}
+
+	pub fn compounding(self) -> Compounding {
+		self.compounding
+	}
+
+	/// Effective rate over one of `periods_per_year` equal periods
+	pub fn periodic(self, periods_per_year: i64) -> f64 {
+		let m = self.compounding.periods_per_year();
+		if m == periods_per_year {
+			return self.apr / m as f64;
+		}
+		f64::powf(
+			1.0 + self.apr / m as f64,
+			m as f64 / periods_per_year as f64,
+		) - 1.0
+	}
+}
+
+impl fmt::Display for Rate {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"{}% APR compounded {}",
+			crate::round_to_decimals(self.apr * 100.0, 3),
+			self.compounding
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::round_to_decimals;
+	use test_case::test_case;
+
+	#[test_case(0.06, Compounding::Monthly => 0.06167781)]
+	#[test_case(0.06, Compounding::Daily => 0.06183131)]
+	#[test_case(0.06, Compounding::Annual => 0.06)]
+	#[test_case(0.075, Compounding::Monthly => 0.0776326)]
+	fn apy(apr: f64, compounding: Compounding) -> f64 {
+		round_to_decimals(Rate::from_apr(apr, compounding).apy(), 8)
+	}
+
+	#[test_case(0.06167781, Compounding::Monthly => 0.06)]
+	#[test_case(0.06183131, Compounding::Daily => 0.06)]
+	#[test_case(0.05, Compounding::Annual => 0.05)]
+	fn from_apy(apy: f64, compounding: Compounding) -> f64 {
+		round_to_decimals(Rate::from_apy(apy, compounding).apr(), 6)
+	}
+
+	#[test_case(Compounding::Monthly, 12 => 0.00625)]
+	#[test_case(Compounding::Monthly, 26 => 0.00287978)]
+	#[test_case(Compounding::Daily, 12 => 0.00626893)]
+	#[test_case(Compounding::Annual, 12 => 0.00604492)]
+	fn periodic(compounding: Compounding, periods_per_year: i64) -> f64 {
+		round_to_decimals(
+			Rate::from_apr(0.075, compounding).periodic(periods_per_year),
+			8,
+		)
+	}
+
+	#[test_case(0.00625, 12, Compounding::Monthly => 0.075)]
+	#[test_case(0.00287978, 26, Compounding::Monthly => 0.075)]
+	#[test_case(0.06, 1, Compounding::Monthly => 0.058411)]
+	fn from_periodic(rate: f64, periods_per_year: i64, compounding: Compounding) -> f64 {
+		round_to_decimals(
+			Rate::from_periodic(rate, periods_per_year, compounding).apr(),
+			6,
+		)
+	}
+
+	#[test]
+	fn display() {
+		assert_eq!(
+			Rate::from_apr(0.075, Compounding::Monthly).to_string(),
+			"7.5% APR compounded monthly"
+		);
+	}
+}
@@ -0,0 +1,148 @@
+use crate::{Loan, Money};
+
+/// How the rate of a variable-rate loan changes over its life
+///
+/// Every change sets a new rate from a given payment on, clamped between `floor` and `cap`. The changes are known
+/// up front, so the optimizer plans around them the same as around everything else.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RateSchedule {
+	pub changes: Vec<RateChange>,
+	/// Highest monthly rate the loan can have
+	pub cap: Option<f64>,
+	/// Lowest monthly rate the loan can have
+	pub floor: Option<f64>,
+	pub on_change: PaymentOnRateChange,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateChange {
+	/// 1-based number of the first payment at the new rate, interest between the previous payment and this one
+	/// is charged at the new rate
+	pub payment: i64,
+	/// Monthly rate, the same way as `Loan::rate`
+	pub rate: f64,
+}
+
+/// What happens to the regular payment when the rate changes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PaymentOnRateChange {
+	/// Work out a new payment that pays off the balance in the payments left
+	#[default]
+	Reamortize,
+	/// Keep paying the same, so the loan is paid off sooner or later than planned
+	KeepPayment,
+}
+
+/// A promotional rate, often 0%, for the first payments of a loan
+///
+/// `Loan::rate` (and its rate schedule, if any) is the rate after the promotion ends. With deferred interest the
+/// interest at that rate is still worked out during the promotion, and all of it is charged if the balance isn't
+/// cleared by the end of it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Promotion {
+	/// Number of payments at the promotional rate
+	pub payments: i64,
+	/// Monthly rate during the promotion
+	pub rate: f64,
+	pub deferred_interest: bool,
+}
+
+impl RateSchedule {
+	/// Monthly rate for a 1-based payment, starting from the loan's own rate
+	pub fn rate_for_payment(&self, initial_rate: f64, payment: i64) -> f64 {
+		let rate = self
+			.changes
+			.iter()
+			.filter(|c| c.payment <= payment)
+			.max_by_key(|c| c.payment)
+			.map_or(initial_rate, |c| c.rate);
+		self.clamp(rate)
+	}
+
+	/// Lowest rate the loan has from a 1-based payment on
+	pub fn lowest_rate_from(&self, initial_rate: f64, payment: i64) -> f64 {
+		self.changes
+			.iter()
+			.filter(|c| c.payment > payment)
+			.map(|c| self.clamp(c.rate))
+			.fold(self.rate_for_payment(initial_rate, payment), f64::min)
+	}
+
+	fn clamp(&self, rate: f64) -> f64 {
+		let rate = self.cap.map_or(rate, |cap| rate.min(cap));
+		self.floor.map_or(rate, |floor| rate.max(floor))
+	}
+}
+
+/// Payment that pays off `balance` in `payments_left` payments at a monthly `rate`, rounded up to the cent
+pub(crate) fn reamortized_payment(
+	loan: &Loan,
+	rate: f64,
+	balance: Money,
+	payments_left: i64,
+) -> Money {
+	let remaining = Loan {
+		initial_value: balance,
+		rate,
+		number_of_payments: payments_left,
+		rate_schedule: None,
+		..loan.clone()
+	};
+	let payment = remaining.calculate_payment_amount();
+	// rounding down would leave a few cents after the last payment
+	Money::from_cents((payment * 100.0 - 1e-6).ceil() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use test_case::test_case;
+
+	fn schedule(cap: Option<f64>, floor: Option<f64>) -> RateSchedule {
+		RateSchedule {
+			changes: vec![
+				RateChange {
+					payment: 13,
+					rate: 0.006,
+				},
+				RateChange {
+					payment: 25,
+					rate: 0.009,
+				},
+				RateChange {
+					payment: 37,
+					rate: 0.002,
+				},
+			],
+			cap,
+			floor,
+			on_change: PaymentOnRateChange::Reamortize,
+		}
+	}
+
+	#[test_case(1, None, None => 0.004)]
+	#[test_case(12, None, None => 0.004)]
+	#[test_case(13, None, None => 0.006)]
+	#[test_case(30, None, None => 0.009)]
+	#[test_case(30, Some(0.007), None => 0.007)]
+	#[test_case(40, None, None => 0.002)]
+	#[test_case(40, None, Some(0.003) => 0.003)]
+	fn rate_for_payment(payment: i64, cap: Option<f64>, floor: Option<f64>) -> f64 {
+		schedule(cap, floor).rate_for_payment(0.004, payment)
+	}
+
+	#[test_case(1, None => 0.002)]
+	#[test_case(1, Some(0.003) => 0.003)]
+	#[test_case(40, None => 0.002)]
+	fn lowest_rate_from(payment: i64, floor: Option<f64>) -> f64 {
+		schedule(None, floor).lowest_rate_from(0.004, payment)
+	}
+
+	#[test_case(0.00625, 1000000, 48 => 24179)]
+	#[test_case(0.0, 120000, 12 => 10000)]
+	#[test_case(0.01, 50000, 1 => 50500)]
+	fn reamortized_payment(rate: f64, balance: i64, payments_left: i64) -> i64 {
+		let loan = Loan::default();
+		super::reamortized_payment(&loan, rate, Money::from_cents(balance), payments_left).cents()
+	}
+}
@@ -0,0 +1,64 @@
+use crate::Money;
+
+/// What happened to a single loan during one period of a payoff plan
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScheduleEntry {
+	/// Index of the loan in the slice passed to the simulation
+	pub loan: usize,
+	pub starting_balance: Money,
+	pub interest: Money,
+	/// The part of the payment covered by the loan's regular payment amount
+	pub scheduled_payment: Money,
+	/// Anything paid on top of the regular payment amount
+	pub extra_payment: Money,
+	pub ending_balance: Money,
+	pub cumulative_interest: Money,
+	/// Mortgage insurance charged with the payment, a cost on top of it
+	pub pmi: Money,
+	/// Taxes and insurance collected with the payment and passed on
+	pub escrow: Money,
+	/// Fees charged on the loan this period
+	pub fees: Money,
+}
+
+impl ScheduleEntry {
+	/// Everything paid this period as it would be on the statement
+	pub fn total_payment(&self) -> Money {
+		self.scheduled_payment + self.extra_payment + self.pmi + self.escrow + self.fees
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchedulePeriod {
+	/// 1-based period number
+	pub period: i64,
+	/// One entry for every loan that still had a balance at the start of the period
+	pub entries: Vec<ScheduleEntry>,
+}
+
+/// Period by period amortization schedule for paying loans in a given ordering
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Schedule {
+	pub ordering: Vec<usize>,
+	pub periods: Vec<SchedulePeriod>,
+}
+
+impl Schedule {
+	/// All entries for a single loan, in period order
+	pub fn entries_for_loan(&self, loan: usize) -> impl Iterator<Item = (i64, &ScheduleEntry)> {
+		self.periods.iter().flat_map(move |p| {
+			p.entries
+				.iter()
+				.filter(move |e| e.loan == loan)
+				.map(move |e| (p.period, e))
+		})
+	}
+
+	pub fn total_interest(&self) -> Money {
+		self.periods
+			.iter()
+			.flat_map(|p| p.entries.iter())
+			.map(|e| e.interest)
+			.sum()
+	}
+}
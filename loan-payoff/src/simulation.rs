@@ -0,0 +1,796 @@
+use crate::rate_schedule::{self, PaymentOnRateChange};
+use crate::strategy::{self, PayoffStrategy};
+use crate::{
+	Accrual, Allocation, Error, ExtraPayments, Loan, LoanKind, Money, PayoffObserver, PayoffResult,
+	Schedule, ScheduleEntry, SchedulePeriod, Timeline, ValidationPolicy,
+};
+
+/// State of a payoff in progress, advanced one period at a time
+///
+/// Cloning it is cheap enough that searches can keep a snapshot at every node.
+#[derive(Clone)]
+pub(crate) struct Simulation<'a> {
+	loans: &'a [&'a Loan],
+	extra_payments: &'a ExtraPayments,
+	pub timeline: Timeline,
+	/// Monthly rate each loan is at now, which only differs from `Loan::rate` for variable-rate loans
+	pub monthly_rates: Vec<f64>,
+	/// Interest rate of each loan for one period of the timeline
+	pub rates: Vec<f64>,
+	/// Regular payment of each loan now, variable-rate loans can be reamortized and revolving loans follow their
+	/// balance
+	pub payment_amounts: Vec<Money>,
+	pub remaining_amounts: Vec<Money>,
+	pub actual_costs: Vec<Money>,
+	pub interest_totals: Vec<Money>,
+	pub payoff_periods: Vec<i64>,
+	pub pmi_costs: Vec<Money>,
+	/// Last period each loan was charged PMI in
+	pub pmi_periods: Vec<i64>,
+	pub penalty_costs: Vec<Money>,
+	pub fee_costs: Vec<Money>,
+	/// How much more than its regular payment each loan's last scheduled payment came to, 0 until it is made
+	pub final_payment_adjustments: Vec<Money>,
+	/// Extra principal paid on each loan so far in the current year, for prepayment caps
+	pub prepaid_this_year: Vec<Money>,
+	/// Whether a prepayment cap held money back in the last period, which then went on to loans further down
+	/// the ordering
+	pub capped: bool,
+	/// Interest a deferred-interest promotion would charge if the balance isn't cleared in time
+	pub deferred_interest: Vec<Money>,
+	/// Interest a student loan has accrued before repayment that isn't part of its principal yet, included in
+	/// `remaining_amounts`
+	pub unpaid_interest: Vec<Money>,
+	/// Moves deferred-interest balances to the front of the ordering just in time to clear them
+	pub promotion_deadlines: bool,
+	/// Number of periods simulated so far
+	pub count: i64,
+}
+
+impl<'a> Simulation<'a> {
+	pub fn new(loans: &'a [&'a Loan], extra_payments: &'a ExtraPayments) -> Simulation<'a> {
+		let timeline = Timeline::for_loans(loans);
+		Simulation {
+			loans,
+			extra_payments,
+			timeline,
+			monthly_rates: loans.iter().map(|l| l.rate).collect(),
+			rates: loans.iter().map(|l| timeline.rate(l)).collect(),
+			payment_amounts: loans.iter().map(|l| l.payment_amount).collect(),
+			remaining_amounts: loans.iter().map(|l| l.initial_value).collect(),
+			actual_costs: vec![Money::ZERO; loans.len()],
+			interest_totals: vec![Money::ZERO; loans.len()],
+			payoff_periods: vec![0; loans.len()],
+			pmi_costs: vec![Money::ZERO; loans.len()],
+			pmi_periods: vec![0; loans.len()],
+			penalty_costs: vec![Money::ZERO; loans.len()],
+			fee_costs: vec![Money::ZERO; loans.len()],
+			final_payment_adjustments: vec![Money::ZERO; loans.len()],
+			prepaid_this_year: vec![Money::ZERO; loans.len()],
+			capped: false,
+			deferred_interest: vec![Money::ZERO; loans.len()],
+			unpaid_interest: vec![Money::ZERO; loans.len()],
+			promotion_deadlines: false,
+			count: 0,
+		}
+	}
+
+	pub fn is_paid_off(&self, loan: usize) -> bool {
+		!self.remaining_amounts[loan].is_positive()
+	}
+
+	/// Whether the loan's regular payment is due in a 1-based period
+	pub fn is_payment_due(&self, loan: usize, period: i64) -> bool {
+		self.timeline.is_payment_period(self.loans[loan], period) && !self.is_deferred(loan, period)
+	}
+
+	/// Whether a student loan's repayment hasn't started by a 1-based period
+	fn is_deferred(&self, loan: usize, period: i64) -> bool {
+		match &self.loans[loan].kind {
+			LoanKind::Student(student) => student.is_deferred(self.timeline.month(period)),
+			_ => false,
+		}
+	}
+
+	/// Everything available for a 1-based period on top of the regular payments of the loans still unpaid
+	///
+	/// The extra payments are budgeted by month, so on a finer timeline each period gets its share of the month.
+	/// The loans already paid off add their regular payment in the periods it would have been due.
+	pub fn extra_for_period(&self, period: i64) -> Money {
+		let monthly = self
+			.extra_payments
+			.amount_for_period(self.timeline.month(period));
+		let extra = if self.timeline.periods_per_year == 12 {
+			monthly
+		} else {
+			monthly.mul_rate(12.0 / self.timeline.periods_per_year as f64)
+		};
+		let freed_payments: Money = (0..self.loans.len())
+			.filter(|&i| self.is_paid_off(i) && self.is_payment_due(i, period))
+			.map(|i| self.payment_amounts[i])
+			.sum();
+		extra + freed_payments
+	}
+
+	/// Lump sums are paid in the first period of their month
+	pub fn lump_sum_for_period(&self, period: i64) -> Money {
+		if self.timeline.starts_month(period) {
+			self.extra_payments
+				.lump_sum_for_period(self.timeline.month(period))
+		} else {
+			Money::ZERO
+		}
+	}
+
+	pub fn is_done(&self) -> bool {
+		(0..self.loans.len()).all(|i| self.is_paid_off(i))
+	}
+
+	/// Pays every loan for one period
+	///
+	/// The allocation's shares of the extra go to their loans, the rest of the extra and the lump sums go to loans
+	/// in the order given.
+	pub fn step(
+		&mut self,
+		ordering: &[usize],
+		allocation: &Allocation,
+		mut entries: Option<&mut Vec<ScheduleEntry>>,
+		observer: &mut dyn PayoffObserver,
+	) {
+		let loans = self.loans;
+		self.count += 1;
+		let count = self.count;
+		self.change_rates(count);
+		self.capitalize_interest(count);
+		self.capped = false;
+		if self.timeline.starts_month(count) && self.timeline.month(count) % 12 == 1 {
+			self.prepaid_this_year.fill(Money::ZERO);
+		}
+		let deadline_ordering;
+		let ordering = if self.promotion_deadlines {
+			deadline_ordering = self.deadline_ordering(ordering, count);
+			&deadline_ordering
+		} else {
+			ordering
+		};
+
+		let (shares, mut extra_amount_this_period) =
+			allocation.split(self.extra_for_period(count), &self.remaining_amounts);
+		for &ix in ordering.iter() {
+			if self.is_paid_off(ix) {
+				continue;
+			}
+
+			let share = shares.get(ix).copied().unwrap_or(Money::ZERO);
+			let starting_balance = self.remaining_amounts[ix];
+			let fees = self.charge_fees(ix, count);
+			let (regular_payment, pmi, escrow) = if self.is_payment_due(ix, count) {
+				let (pmi, escrow) = self.charge_mortgage(ix, count, starting_balance);
+				(
+					self.regular_payment(ix, count, starting_balance),
+					pmi,
+					escrow,
+				)
+			} else {
+				(Money::ZERO, Money::ZERO, Money::ZERO)
+			};
+			let offered = share + extra_amount_this_period;
+			let allowed = self.allowed_prepayment(ix, offered);
+			let blocked = offered - allowed;
+			let amount_to_pay = regular_payment + allowed;
+
+			log::trace!(
+				"BEFORE {}: {}, remaining={}",
+				count,
+				loans[ix],
+				starting_balance
+			);
+			let charge = self.deferred_charge(ix, count);
+			let (amount_paid_this_period, remaining_amount) = if self.is_deferred(ix, count) {
+				self.pay_deferred(ix, starting_balance, amount_to_pay)
+			} else {
+				loans[ix].pay_loan_at(self.rates[ix], starting_balance + charge, amount_to_pay)
+			};
+			self.defer_interest(ix, count, starting_balance);
+			log::trace!(
+				"AFTER {}: {}, remaining={}",
+				count,
+				loans[ix],
+				remaining_amount
+			);
+
+			self.remaining_amounts[ix] = remaining_amount;
+			self.prepaid_this_year[ix] +=
+				(amount_paid_this_period - regular_payment).max(Money::ZERO);
+			extra_amount_this_period = amount_to_pay - amount_paid_this_period + blocked;
+			log::trace!("paying {} .. count={}", amount_paid_this_period, count);
+			self.actual_costs[ix] += amount_paid_this_period;
+
+			let interest = remaining_amount + amount_paid_this_period - starting_balance;
+			self.interest_totals[ix] += interest;
+			if let Some(entries) = entries.as_deref_mut() {
+				let scheduled_payment = amount_paid_this_period.min(regular_payment);
+				entries.push(ScheduleEntry {
+					loan: ix,
+					starting_balance,
+					interest,
+					scheduled_payment,
+					extra_payment: amount_paid_this_period - scheduled_payment,
+					ending_balance: remaining_amount,
+					cumulative_interest: self.interest_totals[ix],
+					pmi,
+					escrow,
+					fees,
+				});
+			}
+
+			if remaining_amount.is_zero() {
+				if regular_payment.is_positive() && self.is_last_payment(ix, count) {
+					self.final_payment_adjustments[ix] =
+						amount_paid_this_period.min(regular_payment) - self.payment_amounts[ix];
+				}
+				self.charge_penalty(ix, count, starting_balance);
+				self.paid_off(ix, observer);
+			}
+		}
+
+		let mut lump_sum = self.lump_sum_for_period(count);
+		for &ix in ordering.iter() {
+			if !lump_sum.is_positive() {
+				break;
+			}
+			if self.is_paid_off(ix) {
+				continue;
+			}
+
+			let balance = self.remaining_amounts[ix];
+			let payment = self.allowed_prepayment(ix, lump_sum.min(balance));
+			if !payment.is_positive() {
+				continue;
+			}
+			log::trace!("paying lump sum {} .. count={}", payment, count);
+			lump_sum -= payment;
+			self.remaining_amounts[ix] -= payment;
+			// the same as a payment during deferment, it goes to the unpaid interest before the principal
+			self.unpaid_interest[ix] = (self.unpaid_interest[ix] - payment).max(Money::ZERO);
+			self.actual_costs[ix] += payment;
+			self.prepaid_this_year[ix] += payment;
+			if let Some(entry) = entries
+				.as_deref_mut()
+				.and_then(|entries| entries.iter_mut().find(|e| e.loan == ix))
+			{
+				entry.extra_payment += payment;
+				entry.ending_balance = self.remaining_amounts[ix];
+			}
+
+			if self.remaining_amounts[ix].is_zero() {
+				self.charge_penalty(ix, count, balance);
+				self.paid_off(ix, observer);
+			}
+		}
+	}
+
+	/// Charges the loan's fees for the month in its first period
+	fn charge_fees(&mut self, ix: usize, period: i64) -> Money {
+		let loan = self.loans[ix];
+		if loan.fees.is_empty() || !self.timeline.starts_month(period) {
+			return Money::ZERO;
+		}
+		let month = self.timeline.month(period);
+		let fees: Money = loan
+			.fees
+			.iter()
+			.filter(|f| f.is_charged(month))
+			.map(|f| f.amount)
+			.sum();
+		self.actual_costs[ix] += fees;
+		self.fee_costs[ix] += fees;
+		fees
+	}
+
+	/// How much of the extra offered to a loan its prepayment cap lets through, the rest goes on to the next loan
+	fn allowed_prepayment(&mut self, ix: usize, offered: Money) -> Money {
+		let loan = self.loans[ix];
+		let limit = match &loan.prepayment {
+			Some(prepayment) if offered.is_positive() => {
+				prepayment.annual_limit(loan.initial_value)
+			}
+			_ => None,
+		};
+		let limit = match limit {
+			Some(limit) => limit,
+			None => return offered,
+		};
+		let allowed = offered.min((limit - self.prepaid_this_year[ix]).max(Money::ZERO));
+		if allowed < offered {
+			self.capped = true;
+		}
+		allowed
+	}
+
+	/// Charges the prepayment penalty when a balance is paid off before the last scheduled payment
+	fn charge_penalty(&mut self, ix: usize, period: i64, balance: Money) {
+		let loan = self.loans[ix];
+		let prepayment = match &loan.prepayment {
+			Some(prepayment) if self.payment_number(ix, period) < loan.number_of_payments => {
+				prepayment
+			}
+			_ => return,
+		};
+		let monthly_rate = loan.rate_per_at(self.monthly_rates[ix], 12);
+		let penalty = prepayment.penalty_for(balance, self.timeline.month(period), monthly_rate);
+		if penalty.is_positive() {
+			log::trace!(
+				"loan '{}' charged a prepayment penalty of {}",
+				loan.name,
+				penalty
+			);
+			self.actual_costs[ix] += penalty;
+			self.penalty_costs[ix] += penalty;
+		}
+	}
+
+	/// Whether a 1-based period leads up to the loan's last scheduled payment, or one after it
+	///
+	/// Only installment and interest-only loans have a last payment that can differ from the rest.
+	fn is_last_payment(&self, ix: usize, period: i64) -> bool {
+		let loan = self.loans[ix];
+		matches!(
+			loan.kind,
+			LoanKind::Installment | LoanKind::InterestOnly { .. }
+		) && self.payment_number(ix, period) >= loan.number_of_payments
+	}
+
+	/// Whether the payment due in a 1-based period pays off the whole balance, as the last payment does for a
+	/// stated payment that doesn't fit the loan's other terms
+	pub fn is_trued_up(&self, ix: usize, period: i64) -> bool {
+		self.loans[ix].validation_policy != ValidationPolicy::Strict
+			&& self.is_last_payment(ix, period)
+	}
+
+	/// 1-based number of the loan's payment a 1-based period leads up to
+	fn payment_number(&self, loan: usize, period: i64) -> i64 {
+		let per_year = self.loans[loan].payment_frequency.periods_per_year();
+		(period - 1) * per_year / self.timeline.periods_per_year + 1
+	}
+
+	/// Monthly rate each loan is at in the next period, including any promotion or scheduled change
+	pub fn rates_ahead(&self) -> Vec<f64> {
+		(0..self.loans.len())
+			.map(|ix| self.loans[ix].rate_for_payment(self.payment_number(ix, self.count + 1)))
+			.collect()
+	}
+
+	/// Lowest rate for one period the loan has from the next period on
+	pub fn lowest_rate_ahead(&self, ix: usize) -> f64 {
+		let loan = self.loans[ix];
+		if !loan.has_variable_rate() {
+			return self.rates[ix];
+		}
+		let rate = loan.lowest_rate_from(self.payment_number(ix, self.count + 1));
+		loan.rate_per_at(rate, self.timeline.periods_per_year)
+	}
+
+	/// Whether the loan's regular payment can still change after this period, with a rate change, because the loan
+	/// isn't a plain installment loan, or because its last payment is trued up next
+	pub fn payment_may_change(&self, ix: usize) -> bool {
+		let loan = self.loans[ix];
+		if loan.kind != LoanKind::Installment || self.is_trued_up(ix, self.count + 1) {
+			return true;
+		}
+		let payment = self.payment_number(ix, self.count + 1);
+		match &loan.rate_schedule {
+			Some(schedule) => {
+				schedule.on_change == PaymentOnRateChange::Reamortize
+					&& (schedule.changes.iter().any(|c| c.payment >= payment)
+						|| loan.is_promotional(payment))
+			}
+			None => false,
+		}
+	}
+
+	/// The ordering with deferred-interest balances moved to the front once waiting any longer would leave them
+	/// uncleared when the promotion ends, the earliest deadline first
+	fn deadline_ordering(&self, ordering: &[usize], period: i64) -> Vec<usize> {
+		let mut urgent: Vec<(i64, usize)> = ordering
+			.iter()
+			.filter_map(|&ix| {
+				self.promotion_deadline(ix, period)
+					.filter(|&deadline| self.must_start_clearing(ix, period, deadline))
+					.map(|deadline| (deadline, ix))
+			})
+			.collect();
+		if urgent.is_empty() {
+			return ordering.to_vec();
+		}
+		urgent.sort_by_key(|&(deadline, _)| deadline);
+		let mut deadline_ordering: Vec<usize> = urgent.iter().map(|&(_, ix)| ix).collect();
+		deadline_ordering.extend(
+			ordering
+				.iter()
+				.filter(|ix| !urgent.iter().any(|u| u.1 == **ix)),
+		);
+		deadline_ordering
+	}
+
+	/// Last period of the promotion, for an unpaid deferred-interest balance whose promotion is still running
+	fn promotion_deadline(&self, ix: usize, period: i64) -> Option<i64> {
+		let loan = self.loans[ix];
+		let promotion = loan.promotion.as_ref().filter(|p| p.deferred_interest)?;
+		let deadline = self.timeline.periods_for_payments(loan, promotion.payments);
+		(!self.is_paid_off(ix) && period <= deadline).then_some(deadline)
+	}
+
+	/// Whether the balance only gets cleared by the deadline if it gets all the extra from this period on
+	///
+	/// Interest at the promotional rate is left out, it is usually 0%.
+	fn must_start_clearing(&self, ix: usize, period: i64, deadline: i64) -> bool {
+		let own_payments: Money = (period..=deadline)
+			.filter(|&p| self.is_payment_due(ix, p))
+			.map(|_| self.payment_amounts[ix])
+			.sum();
+		let later_extra: Money = (period + 1..=deadline)
+			.map(|p| self.extra_for_period(p) + self.lump_sum_for_period(p))
+			.sum();
+		self.remaining_amounts[ix] > own_payments + later_extra
+	}
+
+	/// Keeps track of the interest a deferred-interest promotion is holding back
+	fn defer_interest(&mut self, ix: usize, period: i64, balance: Money) {
+		let loan = self.loans[ix];
+		let payment = self.payment_number(ix, period);
+		if loan.is_promotional(payment)
+			&& loan.promotion.as_ref().is_some_and(|p| p.deferred_interest)
+		{
+			let rate = loan.rate_per_at(
+				loan.standard_rate_for_payment(payment),
+				self.timeline.periods_per_year,
+			);
+			self.deferred_interest[ix] += balance.mul_rate(rate);
+		}
+	}
+
+	/// All the deferred interest once the promotion is over, if the balance wasn't cleared in time
+	fn deferred_charge(&mut self, ix: usize, period: i64) -> Money {
+		if self.loans[ix].is_promotional(self.payment_number(ix, period)) {
+			return Money::ZERO;
+		}
+		let charge = std::mem::replace(&mut self.deferred_interest[ix], Money::ZERO);
+		if charge.is_positive() {
+			log::trace!(
+				"loan '{}' charged {} of deferred interest",
+				self.loans[ix].name,
+				charge
+			);
+		}
+		charge
+	}
+
+	/// Regular payment of the loan in a 1-based period it is due, for the balance it has then
+	///
+	/// Revolving loans pay their minimum, interest-only loans the interest and then a payment worked out again
+	/// for whatever extra went in. A balloon isn't kept as the loan's payment, or it would be freed up for the
+	/// other loans once the loan is paid off.
+	fn regular_payment(&mut self, ix: usize, period: i64, balance: Money) -> Money {
+		let loan = self.loans[ix];
+		let payment = self.payment_number(ix, period);
+		let rate = loan.rate_per_at(
+			self.monthly_rates[ix],
+			loan.payment_frequency.periods_per_year(),
+		);
+		let interest = balance.mul_rate(rate);
+		if self.is_trued_up(ix, period) {
+			return balance + interest;
+		}
+		match loan.kind {
+			LoanKind::Installment => {}
+			LoanKind::Revolving(minimum) => {
+				self.payment_amounts[ix] = minimum.amount(balance, interest);
+			}
+			LoanKind::InterestOnly { payments } if payment <= payments => {
+				self.payment_amounts[ix] = interest;
+			}
+			LoanKind::InterestOnly { payments } if payment == payments + 1 => {
+				self.payment_amounts[ix] = if balance < loan.initial_value {
+					rate_schedule::reamortized_payment(
+						loan,
+						self.monthly_rates[ix],
+						balance,
+						loan.number_of_payments - payments,
+					)
+				} else {
+					loan.payment_amount
+				};
+			}
+			LoanKind::InterestOnly { .. } => {}
+			LoanKind::Balloon { .. } if payment >= loan.number_of_payments => {
+				return balance + interest;
+			}
+			LoanKind::Balloon { .. } => {}
+			LoanKind::Student(_) => {}
+		}
+		self.payment_amounts[ix]
+	}
+
+	/// Charges a mortgage's PMI with a payment on a balance, returns it and the escrow that is passed through
+	fn charge_mortgage(&mut self, ix: usize, period: i64, balance: Money) -> (Money, Money) {
+		let mortgage = match &self.loans[ix].mortgage {
+			Some(mortgage) => mortgage,
+			None => return (Money::ZERO, Money::ZERO),
+		};
+		let pmi = mortgage.pmi_for(balance);
+		if pmi.is_positive() {
+			self.actual_costs[ix] += pmi;
+			self.pmi_costs[ix] += pmi;
+			self.pmi_periods[ix] = period;
+		}
+		(pmi, mortgage.escrow)
+	}
+
+	/// Pays a student loan before repayment starts
+	///
+	/// Unsubsidized interest accrues on the principal alone, and payments go to it before the principal.
+	fn pay_deferred(&mut self, ix: usize, balance: Money, payment: Money) -> (Money, Money) {
+		let interest = match &self.loans[ix].kind {
+			LoanKind::Student(student) if student.accrual == Accrual::Unsubsidized => {
+				(balance - self.unpaid_interest[ix]).mul_rate(self.rates[ix])
+			}
+			_ => Money::ZERO,
+		};
+		let owed = balance + interest;
+		let paid = payment.min(owed);
+		self.unpaid_interest[ix] = (self.unpaid_interest[ix] + interest - paid).max(Money::ZERO);
+		(paid, owed - paid)
+	}
+
+	/// Adds the unpaid interest of student loans to their principal in the first period of a capitalization
+	/// month, and works out the payment when repayment starts
+	fn capitalize_interest(&mut self, period: i64) {
+		if !self.timeline.starts_month(period) {
+			return;
+		}
+		let month = self.timeline.month(period);
+		for (ix, loan) in self.loans.iter().enumerate() {
+			let student = match &loan.kind {
+				LoanKind::Student(student) if !self.is_paid_off(ix) => student,
+				_ => continue,
+			};
+			if !student.capitalizes(month) {
+				continue;
+			}
+			// already part of the balance, it just starts earning interest
+			self.unpaid_interest[ix] = Money::ZERO;
+			if month == student.repayment_start {
+				let balance = self.remaining_amounts[ix];
+				let untouched = student.balance_at_repayment(loan.initial_value, loan.rate_per(12));
+				if balance != untouched {
+					self.payment_amounts[ix] = rate_schedule::reamortized_payment(
+						loan,
+						self.monthly_rates[ix],
+						balance,
+						loan.number_of_payments,
+					);
+				}
+			}
+		}
+	}
+
+	/// Moves variable-rate loans onto the rate for the payment the 1-based period leads up to
+	fn change_rates(&mut self, period: i64) {
+		for (ix, loan) in self.loans.iter().enumerate() {
+			if !loan.has_variable_rate() || self.is_paid_off(ix) {
+				continue;
+			}
+			let payment = self.payment_number(ix, period);
+			let rate = loan.rate_for_payment(payment);
+			if rate == self.monthly_rates[ix] {
+				continue;
+			}
+
+			log::trace!(
+				"loan '{}' changes to rate {} at payment {}",
+				loan.name,
+				rate,
+				payment
+			);
+			self.monthly_rates[ix] = rate;
+			self.rates[ix] = loan.rate_per_at(rate, self.timeline.periods_per_year);
+			let term = match loan.kind {
+				LoanKind::Balloon {
+					amortization_payments,
+				} => amortization_payments,
+				_ => loan.number_of_payments,
+			};
+			let payments_left = term - (payment - 1);
+			let reamortize = loan
+				.rate_schedule
+				.as_ref()
+				.is_some_and(|s| s.on_change == PaymentOnRateChange::Reamortize);
+			if reamortize && payments_left > 0 {
+				self.payment_amounts[ix] = rate_schedule::reamortized_payment(
+					loan,
+					rate,
+					self.remaining_amounts[ix],
+					payments_left,
+				);
+			}
+		}
+	}
+
+	fn paid_off(&mut self, ix: usize, observer: &mut dyn PayoffObserver) {
+		self.payoff_periods[ix] = self.count;
+		observer.loan_paid_off(self.loans[ix], self.count);
+	}
+}
+
+/// The most periods of the timeline any ordering should need, running past this means the loans never get paid off
+pub(crate) fn max_number_payments(loans: &[&Loan]) -> i64 {
+	let timeline = Timeline::for_loans(loans);
+	loans
+		.iter()
+		.map(|l| {
+			let payments = if l.has_level_payments() {
+				l.number_of_payments
+			} else {
+				regular_payoff(l).1
+			};
+			timeline.periods_for_payments(l, payments)
+		})
+		.max()
+		.unwrap_or(0)
+}
+
+/// Cost and number of payments of the loan with only its regular payments
+///
+/// Fixed-rate loans cost their payment times the number of payments. A rate schedule or promotion can change the
+/// payment or how long the same payment takes, and a revolving loan's minimum payment shrinks with its balance, so
+/// those loans are run through on their own. One that still has a balance after ten times its number of payments,
+/// or a hundred years of minimum payments, is never going to be paid off, that many payments is returned.
+pub(crate) fn regular_payoff(loan: &Loan) -> (Money, i64) {
+	if loan.has_level_payments() {
+		return (
+			loan.payment_amount * loan.number_of_payments,
+			loan.number_of_payments,
+		);
+	}
+	let loans = [loan];
+	let extra_payments = ExtraPayments::default();
+	let mut simulation = Simulation::new(&loans, &extra_payments);
+	let limit = match &loan.kind {
+		LoanKind::Revolving(_) => loan.payment_frequency.periods_per_year() * 100,
+		LoanKind::Student(student) => {
+			loan.number_of_payments.max(1) * 10
+				+ simulation
+					.timeline
+					.periods_for_payments(loan, student.repayment_start)
+		}
+		_ => loan.number_of_payments.max(1) * 10,
+	};
+	while !simulation.is_done() && simulation.count < limit {
+		simulation.step(
+			&[0],
+			&Allocation::default(),
+			None,
+			&mut crate::SilentObserver,
+		);
+	}
+	(simulation.actual_costs[0], simulation.count)
+}
+
+/// Checks the loans in the order given, so the first bad one in that order is the one reported
+pub(crate) fn validate_loans(loans: &[&Loan], ordering: &[usize]) -> Result<(), Error> {
+	for &i in ordering.iter() {
+		if let Err(issues) = loans[i].validate() {
+			return Err(Error::InvalidLoan {
+				index: i,
+				name: loans[i].name.clone(),
+				issues,
+			});
+		}
+	}
+	Ok(())
+}
+
+pub(crate) fn simulate(
+	loans: &[&Loan],
+	extra_payments: &ExtraPayments,
+	strategy: &dyn PayoffStrategy,
+	mut schedule: Option<&mut Schedule>,
+	observer: &mut dyn PayoffObserver,
+) -> Result<PayoffResult, Error> {
+	let mut simulation = Simulation::new(loans, extra_payments);
+	simulation.promotion_deadlines = strategy.promotion_deadlines();
+	let next_ordering = |remaining_amounts: &[Money], rates: &[f64]| {
+		let ordering = strategy.ordering(loans, remaining_amounts, rates);
+		if !strategy::is_complete_ordering(&ordering, loans.len()) {
+			return Err(Error::OtherError(format!(
+				"strategy returned {:?}, which is not an ordering of {} loans",
+				ordering,
+				loans.len()
+			)));
+		}
+		let allocation = strategy.allocation(loans, remaining_amounts);
+		allocation
+			.validate(loans.len())
+			.map_err(Error::OtherError)?;
+		Ok((ordering, allocation))
+	};
+	let (ordering, allocation) =
+		next_ordering(&simulation.remaining_amounts, &simulation.rates_ahead())?;
+	log::trace!("Pay loans {:?}", ordering);
+
+	validate_loans(loans, &ordering)?;
+	let is_debt_snowball = ordering
+		.windows(2)
+		.all(|w| loans[w[0]].initial_value <= loans[w[1]].initial_value);
+	let expected_costs: Vec<Money> = loans.iter().map(|l| regular_payoff(l).0).collect();
+	let max_number_payments = max_number_payments(loans);
+
+	while !simulation.is_done() {
+		if simulation.count >= max_number_payments {
+			for (ix, loan) in loans.iter().enumerate() {
+				log::error!(
+					"Loan={}, Remaining Amount={}",
+					loan,
+					simulation.remaining_amounts[ix]
+				);
+			}
+			log::error!(
+				"Went too long, should have finished in at most {} periods",
+				max_number_payments
+			);
+			return Err(Error::LoanGoesToInf);
+		}
+
+		let (period_ordering, period_allocation) = if simulation.count == 0 {
+			(ordering.clone(), allocation.clone())
+		} else {
+			next_ordering(&simulation.remaining_amounts, &simulation.rates_ahead())?
+		};
+		let mut period_entries = Vec::new();
+		simulation.step(
+			&period_ordering,
+			&period_allocation,
+			schedule.as_ref().map(|_| &mut period_entries),
+			observer,
+		);
+
+		if let Some(schedule) = schedule.as_deref_mut() {
+			schedule.periods.push(SchedulePeriod {
+				period: simulation.count,
+				entries: period_entries,
+			});
+		}
+	}
+
+	let expected_costs_total: Money = expected_costs.iter().sum();
+	let actual_costs_total: Money = simulation.actual_costs.iter().sum();
+	let savings_total = expected_costs_total - actual_costs_total;
+
+	log::trace!(
+		"Pay loans with ordering {:?}, total amount {}, savings {}",
+		ordering,
+		actual_costs_total,
+		savings_total
+	);
+
+	Ok(PayoffResult {
+		ordering,
+		allocation,
+		promotion_deadlines: simulation.promotion_deadlines,
+		is_debt_snowball,
+		expected_costs,
+		actual_costs: simulation.actual_costs,
+		payoff_periods: simulation.payoff_periods,
+		pmi_costs: simulation.pmi_costs,
+		pmi_periods: simulation.pmi_periods,
+		penalty_costs: simulation.penalty_costs,
+		fee_costs: simulation.fee_costs,
+		final_payment_adjustments: simulation.final_payment_adjustments,
+		total_periods: simulation.count,
+		periods_per_year: simulation.timeline.periods_per_year,
+		expected_costs_total,
+		actual_costs_total,
+		savings_total,
+	})
+}
@@ -0,0 +1,181 @@
+use crate::{Allocation, Loan, Money};
+
+/// Decides which loans get extra money first
+///
+/// The simulation asks for an ordering at the start of every period. The extra amount goes to the first
+/// loan in the ordering that still has a balance, and whatever that loan doesn't need moves on to the next.
+/// The ordering has to contain every loan exactly once, including ones that are already paid off.
+pub trait PayoffStrategy {
+	/// `balances` are the balances at the start of the period and `rates` the monthly rates the loans are at for
+	/// it, promotional and scheduled rates included, both indexed the same way as `loans`
+	fn ordering(&self, loans: &[&Loan], balances: &[Money], rates: &[f64]) -> Vec<usize>;
+
+	/// Shares of the extra amount that go to particular loans before the rest follows the ordering
+	fn allocation(&self, _loans: &[&Loan], _balances: &[Money]) -> Allocation {
+		Allocation::default()
+	}
+
+	/// Whether deferred-interest balances jump to the front of the ordering just in time to clear them
+	fn promotion_deadlines(&self) -> bool {
+		false
+	}
+}
+
+/// Smallest balance first
+pub struct DebtSnowball;
+
+impl PayoffStrategy for DebtSnowball {
+	fn ordering(&self, loans: &[&Loan], balances: &[Money], _rates: &[f64]) -> Vec<usize> {
+		let mut ordering: Vec<usize> = (0..loans.len()).collect();
+		ordering.sort_by_key(|&i| balances[i]);
+		ordering
+	}
+}
+
+/// Highest interest rate first, the rate the loan is at now rather than the one it ends up at
+pub struct DebtAvalanche;
+
+impl PayoffStrategy for DebtAvalanche {
+	fn ordering(&self, loans: &[&Loan], _balances: &[Money], rates: &[f64]) -> Vec<usize> {
+		let mut ordering: Vec<usize> = (0..loans.len()).collect();
+		ordering.sort_by(|&a, &b| rates[b].total_cmp(&rates[a]));
+		ordering
+	}
+}
+
+/// Highest regular payment first, freeing up the most cash flow once it is paid off
+pub struct HighestPaymentFirst;
+
+impl PayoffStrategy for HighestPaymentFirst {
+	fn ordering(&self, loans: &[&Loan], _balances: &[Money], _rates: &[f64]) -> Vec<usize> {
+		let mut ordering: Vec<usize> = (0..loans.len()).collect();
+		ordering.sort_by_key(|&i| std::cmp::Reverse(loans[i].payment_amount));
+		ordering
+	}
+}
+
+/// An ordering chosen up front that never changes
+#[derive(Clone, Debug, PartialEq)]
+pub struct FixedOrder(pub Vec<usize>);
+
+impl PayoffStrategy for FixedOrder {
+	fn ordering(&self, _loans: &[&Loan], _balances: &[Money], _rates: &[f64]) -> Vec<usize> {
+		self.0.clone()
+	}
+}
+
+/// Sets aside shares of the extra amount for particular loans and lets another strategy order the rest
+pub struct Split {
+	pub allocation: Allocation,
+	pub then: Box<dyn PayoffStrategy>,
+}
+
+impl PayoffStrategy for Split {
+	fn ordering(&self, loans: &[&Loan], balances: &[Money], rates: &[f64]) -> Vec<usize> {
+		self.then.ordering(loans, balances, rates)
+	}
+
+	fn allocation(&self, _loans: &[&Loan], _balances: &[Money]) -> Allocation {
+		self.allocation.clone()
+	}
+
+	fn promotion_deadlines(&self) -> bool {
+		self.then.promotion_deadlines()
+	}
+}
+
+/// Follows another strategy, except that a deferred-interest balance goes to the front of the ordering once
+/// waiting any longer would leave it uncleared when its promotion ends
+///
+/// That way the extra goes elsewhere for as long as possible and the promotion's interest is never charged,
+/// which no single ordering can do.
+pub struct PromotionDeadlines(pub Box<dyn PayoffStrategy>);
+
+impl PayoffStrategy for PromotionDeadlines {
+	fn ordering(&self, loans: &[&Loan], balances: &[Money], rates: &[f64]) -> Vec<usize> {
+		self.0.ordering(loans, balances, rates)
+	}
+
+	fn allocation(&self, loans: &[&Loan], balances: &[Money]) -> Allocation {
+		self.0.allocation(loans, balances)
+	}
+
+	fn promotion_deadlines(&self) -> bool {
+		true
+	}
+}
+
+pub(crate) fn is_complete_ordering(ordering: &[usize], loan_count: usize) -> bool {
+	let mut seen = vec![false; loan_count];
+	ordering.len() == loan_count
+		&& ordering
+			.iter()
+			.all(|&i| i < loan_count && !std::mem::replace(&mut seen[i], true))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use test_case::test_case;
+
+	fn loan(initial_value: i64, rate: f64, payment_amount: i64) -> Loan {
+		Loan {
+			initial_value: Money::from_cents(initial_value),
+			rate,
+			payment_amount: Money::from_cents(payment_amount),
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn builtin_orderings() {
+		let loans = [
+			loan(500, 0.01, 300),
+			loan(100, 0.03, 200),
+			loan(300, 0.02, 100),
+		];
+		let loans: Vec<&Loan> = loans.iter().collect();
+		let balances: Vec<Money> = loans.iter().map(|l| l.initial_value).collect();
+		let rates: Vec<f64> = loans.iter().map(|l| l.rate).collect();
+
+		assert_eq!(
+			DebtSnowball.ordering(&loans, &balances, &rates),
+			vec![1, 2, 0]
+		);
+		assert_eq!(
+			DebtAvalanche.ordering(&loans, &balances, &rates),
+			vec![1, 2, 0]
+		);
+		assert_eq!(
+			HighestPaymentFirst.ordering(&loans, &balances, &rates),
+			vec![0, 1, 2]
+		);
+		assert_eq!(
+			FixedOrder(vec![2, 0, 1]).ordering(&loans, &balances, &rates),
+			vec![2, 0, 1]
+		);
+
+		// snowball follows the current balances, not the original ones
+		let balances = [Money::from_cents(50), Money::from_cents(100), Money::ZERO];
+		assert_eq!(
+			DebtSnowball.ordering(&loans, &balances, &rates),
+			vec![2, 0, 1]
+		);
+
+		// avalanche follows the rates the loans are at now, like a 0% promotion
+		let rates = [0.01, 0.0, 0.02];
+		assert_eq!(
+			DebtAvalanche.ordering(&loans, &balances, &rates),
+			vec![2, 0, 1]
+		);
+	}
+
+	#[test_case(&[2, 0, 1], 3 => true)]
+	#[test_case(&[0, 1], 3 => false)]
+	#[test_case(&[0, 1, 1], 3 => false)]
+	#[test_case(&[0, 1, 3], 3 => false)]
+	#[test_case(&[], 0 => true)]
+	fn is_complete_ordering(ordering: &[usize], loan_count: usize) -> bool {
+		super::is_complete_ordering(ordering, loan_count)
+	}
+}
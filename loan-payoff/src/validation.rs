@@ -0,0 +1,320 @@
+use crate::finance::{self, PaymentTiming};
+use crate::{within_five_cents, Loan, LoanKind, LoanTerm, Money};
+use std::fmt;
+
+/// How closely a loan's payment has to match the one worked out from its other terms
+///
+/// Lenders round and work out payments their own way, so the payment on a statement can be off by more than a few
+/// cents. Outside of `Strict` the stated payment is paid as is and the last scheduled payment pays off whatever is
+/// left, see `Loan::final_payment_adjustment`.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum ValidationPolicy {
+	/// Within 5 cents, and every payment is `payment_amount` but a smaller last one
+	#[default]
+	Strict,
+	/// Within the given amount
+	Tolerance(Money),
+	/// Any payment that covers the interest, as the lender states it
+	TrustStatement,
+}
+
+/// A field of a loan that doesn't make sense, with what it should be and how to fix it
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationIssue {
+	/// Name of the field as it is in the CSV file, like `payment_amount`
+	pub field: &'static str,
+	pub expected: String,
+	pub actual: String,
+	pub suggestion: String,
+}
+
+impl ValidationIssue {
+	fn new(
+		field: &'static str,
+		expected: impl fmt::Display,
+		actual: impl fmt::Display,
+		suggestion: impl Into<String>,
+	) -> ValidationIssue {
+		ValidationIssue {
+			field,
+			expected: expected.to_string(),
+			actual: actual.to_string(),
+			suggestion: suggestion.into(),
+		}
+	}
+}
+
+impl fmt::Display for ValidationIssue {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"{} is {}, expected {}: {}",
+			self.field, self.actual, self.expected, self.suggestion
+		)
+	}
+}
+
+/// Every issue with the loan, the payment is only checked against the other terms once they make sense
+pub(crate) fn issues(loan: &Loan) -> Vec<ValidationIssue> {
+	let mut issues = Vec::new();
+	let is_revolving = matches!(loan.kind, LoanKind::Revolving(_));
+	if !loan.initial_value.is_positive() {
+		issues.push(ValidationIssue::new(
+			"initial_value",
+			"more than 0.00",
+			loan.initial_value,
+			"enter the balance owed as a positive amount",
+		));
+	}
+	if !(loan.rate >= 0.0 && loan.rate.is_finite()) {
+		issues.push(ValidationIssue::new(
+			"rate",
+			"a rate of 0 or more",
+			loan.rate,
+			"enter the monthly rate, APR / 12, or use the apr column",
+		));
+	}
+	if !is_revolving && loan.number_of_payments < 1 {
+		issues.push(ValidationIssue::new(
+			"number_of_payments",
+			"at least 1",
+			loan.number_of_payments,
+			"enter the number of payments left, or a minimum_percent for a revolving loan",
+		));
+	}
+	if !loan.payment_amount.is_positive() {
+		issues.push(ValidationIssue::new(
+			"payment_amount",
+			"more than 0.00",
+			loan.payment_amount,
+			"enter the regular payment from the statement",
+		));
+	}
+	issues.extend(kind_issues(loan));
+	if let Some(promotion) = &loan.promotion {
+		if promotion.payments < 0 || !(promotion.rate >= 0.0 && promotion.rate.is_finite()) {
+			issues.push(ValidationIssue::new(
+				"promo_payments",
+				"0 or more payments at a rate of 0 or more",
+				format!("{} payments at {}", promotion.payments, promotion.rate),
+				"check the promotion's length and rate",
+			));
+		}
+	}
+	if let Some(mortgage) = &loan.mortgage {
+		if !mortgage.home_value.is_positive() {
+			issues.push(ValidationIssue::new(
+				"home_value",
+				"more than 0.00",
+				mortgage.home_value,
+				"enter the value of the home when the loan was taken out",
+			));
+		}
+	}
+	if !issues.is_empty() || is_revolving {
+		return issues;
+	}
+
+	let repayment = loan.repayment_loan();
+	let interest = repayment.initial_value.mul_rate(repayment.rate_per_at(
+		repayment.rate_for_payment(1),
+		loan.payment_frequency.periods_per_year(),
+	));
+	if loan.payment_amount <= interest {
+		issues.push(ValidationIssue::new(
+			"payment_amount",
+			format!("more than {}", interest),
+			loan.payment_amount,
+			format!(
+				"the payment doesn't cover the {} of interest a period, check the rate and payment",
+				interest
+			),
+		));
+	} else if let Some(expected) = expected_payment_amount(loan) {
+		let mismatch = match loan.validation_policy {
+			ValidationPolicy::Strict if !within_five_cents(expected, loan.payment_amount) => {
+				Some("5 cents".to_owned())
+			}
+			ValidationPolicy::Tolerance(tolerance)
+				if (expected - loan.payment_amount).abs() > tolerance =>
+			{
+				Some(tolerance.to_string())
+			}
+			_ => None,
+		};
+		if let Some(tolerance) = mismatch.filter(|_| !has_smaller_last_payment(loan)) {
+			issues.push(ValidationIssue::new(
+				"payment_amount",
+				format!("{} give or take {}", expected, tolerance),
+				loan.payment_amount,
+				format!(
+					"use {}, which pays off {} in {} payments, or check the other terms",
+					expected, repayment.initial_value, loan.number_of_payments
+				),
+			));
+		}
+	}
+	issues
+}
+
+fn kind_issues(loan: &Loan) -> Vec<ValidationIssue> {
+	match &loan.kind {
+		LoanKind::InterestOnly { payments }
+			if *payments < 0 || *payments >= loan.number_of_payments =>
+		{
+			vec![ValidationIssue::new(
+				"interest_only_payments",
+				format!("fewer than number_of_payments, {}", loan.number_of_payments),
+				payments,
+				"leave some payments to pay off the principal",
+			)]
+		}
+		LoanKind::Balloon {
+			amortization_payments,
+		} if *amortization_payments < loan.number_of_payments => vec![ValidationIssue::new(
+			"balloon_amortization_payments",
+			format!("at least number_of_payments, {}", loan.number_of_payments),
+			amortization_payments,
+			"enter the term the payment is worked out for, which is longer than the loan",
+		)],
+		LoanKind::Student(student) if student.repayment_start < 1 => vec![ValidationIssue::new(
+			"repayment_start",
+			"at least 1",
+			student.repayment_start,
+			"enter the month of the first payment",
+		)],
+		_ => Vec::new(),
+	}
+}
+
+/// Whether the number of payments was worked out from the payment and rounded up, leaving a smaller last payment
+///
+/// Only a solved term is let off, a payment entered with its term still has to fit it.
+fn has_smaller_last_payment(loan: &Loan) -> bool {
+	loan.solved_term == Some(LoanTerm::NumberOfPayments)
+		&& final_payment_adjustment(loan)
+			.is_some_and(|adjustment| adjustment.is_negative() && adjustment > -loan.payment_amount)
+}
+
+/// The payment that pays the loan off in its number of payments, if there is a single one
+pub(crate) fn expected_payment_amount(loan: &Loan) -> Option<Money> {
+	amortizing_loan(loan).map(|amortizing| Money::from_f64(amortizing.calculate_payment_amount()))
+}
+
+/// What is left to pay with the last scheduled payment when every one before it is `payment_amount`
+///
+/// Only installment and interest-only loans are trued up that way, a balloon loan already pays off the rest at
+/// the end. With a rate schedule the rate the payments see isn't known up front.
+pub(crate) fn final_payment_adjustment(loan: &Loan) -> Option<Money> {
+	if !matches!(
+		loan.kind,
+		LoanKind::Installment | LoanKind::InterestOnly { .. }
+	) || loan.rate_schedule.is_some()
+	{
+		return None;
+	}
+	let amortizing = amortizing_loan(loan)?;
+	let remaining = finance::fv(
+		amortizing.payment_rate(),
+		amortizing.number_of_payments as f64,
+		-loan.payment_amount.to_f64(),
+		amortizing.initial_value.to_f64(),
+		PaymentTiming::End,
+	)?;
+	Some(-Money::from_f64(remaining))
+}
+
+/// The loan as it is paid off by level payments, if it is
+///
+/// A promotion that lasts the whole loan is just a different rate, but one that ends part way through leaves no
+/// payment that amortizes the loan, so it has to be taken as given. Revolving loans have no term to check against,
+/// interest-only and balloon loans are checked against the payments that amortize them.
+fn amortizing_loan(loan: &Loan) -> Option<Loan> {
+	if let LoanKind::Revolving(_) = loan.kind {
+		return None;
+	}
+	let amortizing = Loan {
+		number_of_payments: loan.amortization_payments(),
+		..loan.repayment_loan()
+	};
+	match &loan.promotion {
+		Some(promotion) if promotion.payments >= loan.number_of_payments => Some(Loan {
+			rate: promotion.rate,
+			rate_schedule: None,
+			promotion: None,
+			..amortizing
+		}),
+		Some(_) => None,
+		None => Some(amortizing),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::MinimumPayment;
+	use test_case::test_case;
+
+	fn loan(initial_value: i64, rate: f64, number_of_payments: i64, payment_amount: i64) -> Loan {
+		Loan::new(
+			"l1".to_owned(),
+			Money::from_cents(initial_value),
+			rate,
+			number_of_payments,
+			Money::from_cents(payment_amount),
+		)
+	}
+
+	#[test_case(loan(1000000, 0.00625, 48, 24179) => Vec::<&str>::new())]
+	#[test_case(loan(1000000, 0.00625, 48, 24100) => vec!["payment_amount"])]
+	#[test_case(loan(1000000, 0.00625, 48, 6000) => vec!["payment_amount"])] // less than the interest
+	#[test_case(Loan { solved_term: Some(LoanTerm::NumberOfPayments), ..loan(1000000, 0.005, 37, 30000) } => Vec::<&str>::new())] // smaller last payment
+	#[test_case(Loan { solved_term: Some(LoanTerm::NumberOfPayments), ..loan(1000000, 0.005, 38, 30000) } => vec!["payment_amount"])] // paid off a payment early
+	#[test_case(loan(1000000, 0.005, 37, 30000) => vec!["payment_amount"])] // not solved, so the payment has to fit
+	#[test_case(loan(30000000, 0.005, 360, 180000) => vec!["payment_amount"])] // 1798.65 give or take 5 cents
+	#[test_case(loan(-1000000, f64::NAN, 0, 0) => vec!["initial_value", "rate", "number_of_payments", "payment_amount"])]
+	#[test_case(Loan { kind: LoanKind::Balloon { amortization_payments: 24 }, ..loan(1000000, 0.00625, 48, 24179) } => vec!["balloon_amortization_payments"])]
+	#[test_case(Loan { kind: LoanKind::InterestOnly { payments: 48 }, ..loan(1000000, 0.00625, 48, 24179) } => vec!["interest_only_payments"])]
+	fn issues(loan: Loan) -> Vec<&'static str> {
+		super::issues(&loan).iter().map(|i| i.field).collect()
+	}
+
+	#[test_case(ValidationPolicy::Strict, 24100 => vec!["payment_amount"])]
+	#[test_case(ValidationPolicy::Tolerance(Money::from_cents(50)), 24100 => vec!["payment_amount"])]
+	#[test_case(ValidationPolicy::Tolerance(Money::from_cents(100)), 24100 => Vec::<&str>::new())]
+	#[test_case(ValidationPolicy::TrustStatement, 24100 => Vec::<&str>::new())]
+	#[test_case(ValidationPolicy::TrustStatement, 6000 => vec!["payment_amount"])] // still has to cover the interest
+	fn policy(validation_policy: ValidationPolicy, payment_amount: i64) -> Vec<&'static str> {
+		let loan = Loan {
+			validation_policy,
+			..loan(1000000, 0.00625, 48, payment_amount)
+		};
+		super::issues(&loan).iter().map(|i| i.field).collect()
+	}
+
+	#[test_case(loan(1000000, 0.00625, 48, 24179) => Some(-5))]
+	#[test_case(loan(1000000, 0.00625, 48, 24100) => Some(4401))]
+	#[test_case(loan(1000000, 0.00625, 48, 25000) => Some(-45797))] // paid off early
+	#[test_case(Loan { kind: LoanKind::Balloon { amortization_payments: 360 }, ..loan(1000000, 0.00625, 48, 6700) } => None)]
+	#[test_case(Loan::revolving("l1".to_owned(), Money::from_cents(100000), 0.015, MinimumPayment::default()) => None)]
+	fn final_payment_adjustment(loan: Loan) -> Option<i64> {
+		super::final_payment_adjustment(&loan).map(Money::cents)
+	}
+
+	#[test]
+	fn display() {
+		let issues = super::issues(&loan(1000000, 0.00625, 48, 24100));
+		assert_eq!(
+			issues[0].to_string(),
+			"payment_amount is 241.00, expected 241.79 give or take 5 cents: use 241.79, which pays off 10000.00 in 48 payments, or check the other terms"
+		);
+		let tolerant = Loan {
+			validation_policy: ValidationPolicy::Tolerance(Money::from_cents(50)),
+			..loan(1000000, 0.00625, 48, 24100)
+		};
+		assert_eq!(
+			super::issues(&tolerant)[0].expected,
+			"241.79 give or take 0.50"
+		);
+	}
+}
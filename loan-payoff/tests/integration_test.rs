@@ -1,33 +1,58 @@
-use loan_payoff;
+use loan_payoff::{
+	Accrual, Allocation, AllocationRule, Compounding, DebtAvalanche, ExtraPaymentRange,
+	ExtraPayments, Fee, FeeFrequency, FixedOrder, Loan, LoanKind, LumpSum, MinimumPayment, Money,
+	Mortgage, PaymentFrequency, PaymentOnRateChange, PayoffObserver, PayoffResult, PenaltyCharge,
+	Prepayment, PrepaymentPenalty, Promotion, RateChange, RateSchedule, Share, SilentObserver,
+	Split, StudentLoan, ValidationPolicy,
+};
+
+#[derive(Default)]
+struct RecordingObserver {
+	paid_off: Vec<(String, i64)>,
+	orderings: Vec<Vec<usize>>,
+}
+
+impl PayoffObserver for RecordingObserver {
+	fn loan_paid_off(&mut self, loan: &Loan, period: i64) {
+		self.paid_off.push((loan.name.clone(), period));
+	}
+
+	fn ordering_paid_off(&mut self, _loans: &[&Loan], result: &PayoffResult) {
+		self.orderings.push(result.ordering.clone());
+	}
+}
 
 #[test]
 fn basic_payoff() -> Result<(), loan_payoff::Error> {
 	let loan1 = loan_payoff::Loan {
 		name: "l1".to_owned(),
-		initial_value: 10000.0,
+		initial_value: Money::from_f64(10000.0),
 		rate: 0.00625, // 7.5% annual
 		number_of_payments: 48,
-		payment_amount: 241.79,
+		payment_amount: Money::from_f64(241.79),
+		..Default::default()
 	};
 	let loan2 = loan_payoff::Loan {
 		name: "l2".to_owned(),
-		initial_value: 12000.0,
+		initial_value: Money::from_f64(12000.0),
 		rate: 0.02083, // 25% annual
 		number_of_payments: 36,
-		payment_amount: 477.12,
+		payment_amount: Money::from_f64(477.12),
+		..Default::default()
 	};
 	let loans = vec![&loan1, &loan2];
-	let extra_amount = 100.0;
-	let mut ordering = vec![0; loans.len()];
-	for i in 0..ordering.len() {
-		ordering[i] = i
-	}
-	let (is_debt_snowball, actual_costs_total, savings_total) =
-		loan_payoff::pay_loans(&loans, extra_amount, &ordering)?;
+	let extra_amount = Money::from_f64(100.0);
+	let ordering: Vec<usize> = (0..loans.len()).collect();
+	let result = loan_payoff::pay_loans(&loans, extra_amount, &ordering)?;
 
-	assert!(is_debt_snowball);
-	assert!(actual_costs_total == 28244.9);
-	assert!(savings_total == 537.34);
+	assert!(result.is_debt_snowball);
+	assert!(result.actual_costs_total == Money::from_cents(2822801));
+	assert!(result.savings_total == Money::from_cents(55423));
+	assert!(result.expected_costs_total == Money::from_cents(2878224));
+	assert!(result.ordering == ordering);
+	assert!(result.total_periods == *result.payoff_periods.iter().max().unwrap());
+	assert!(result.payoff_periods[0] < result.payoff_periods[1]);
+	assert!(result.savings(0) + result.savings(1) == result.savings_total);
 	Ok(())
 }
 
@@ -35,26 +60,1240 @@ fn basic_payoff() -> Result<(), loan_payoff::Error> {
 fn outperform_debt_snowball() -> Result<(), loan_payoff::Error> {
 	let loan1 = loan_payoff::Loan {
 		name: "l1".to_owned(),
-		initial_value: 10000.0,
+		initial_value: Money::from_f64(10000.0),
 		rate: 0.00625, // 7.5% annual
 		number_of_payments: 48,
-		payment_amount: 241.79,
+		payment_amount: Money::from_f64(241.79),
+		..Default::default()
 	};
 	let loan2 = loan_payoff::Loan {
 		name: "l2".to_owned(),
-		initial_value: 12000.0,
+		initial_value: Money::from_f64(12000.0),
 		rate: 8.0 / 12.0 / 100.0, // 8% annual
 		number_of_payments: 48,
-		payment_amount: 292.96,
+		payment_amount: Money::from_f64(292.96),
+		..Default::default()
 	};
 	let loans = vec![&loan1, &loan2];
-	let extra_amount = 100.0;
+	let extra_amount = Money::from_f64(100.0);
 	let optimal_payoff = loan_payoff::pay_loans_all_orderings(&loans, extra_amount)?;
 
-	assert!(optimal_payoff.is_debt_snowball == false);
-	assert!(optimal_payoff.savings_over_debt_snowball == 32.15);
-	assert!(optimal_payoff.savings == 667.26);
-	assert!(optimal_payoff.ordering.get(0) == Some(&1));
-	assert!(optimal_payoff.ordering.get(1) == Some(&0));
+	assert!(!optimal_payoff.best.is_debt_snowball);
+	assert!(optimal_payoff.savings_over_debt_snowball == Money::from_cents(3237));
+	assert!(optimal_payoff.best.savings_total == Money::from_cents(68687));
+	assert!(optimal_payoff.best.ordering.first() == Some(&1));
+	assert!(optimal_payoff.best.ordering.get(1) == Some(&0));
+	let debt_snowball = optimal_payoff.debt_snowball.unwrap();
+	assert!(debt_snowball.is_debt_snowball);
+	assert!(debt_snowball.ordering == vec![0, 1]);
+	assert!(debt_snowball.savings_total == Money::from_cents(65450));
+	Ok(())
+}
+
+#[test]
+fn amortization_schedule() -> Result<(), loan_payoff::Error> {
+	let loan1 = loan_payoff::Loan {
+		name: "l1".to_owned(),
+		initial_value: Money::from_f64(10000.0),
+		rate: 0.00625, // 7.5% annual
+		number_of_payments: 48,
+		payment_amount: Money::from_f64(241.79),
+		..Default::default()
+	};
+	let loan2 = loan_payoff::Loan {
+		name: "l2".to_owned(),
+		initial_value: Money::from_f64(12000.0),
+		rate: 0.02083, // 25% annual
+		number_of_payments: 36,
+		payment_amount: Money::from_f64(477.12),
+		..Default::default()
+	};
+	let loans = vec![&loan1, &loan2];
+	let ordering = vec![0, 1];
+	let schedule = loan_payoff::amortization_schedule(
+		&loans,
+		Money::from_f64(100.0),
+		&FixedOrder(ordering.clone()),
+	)?;
+	let result = loan_payoff::pay_loans(&loans, Money::from_f64(100.0), &ordering)?;
+
+	let first = &schedule.periods[0].entries[0];
+	assert!(first.loan == 0);
+	assert!(first.starting_balance == Money::from_cents(1000000));
+	assert!(first.interest == Money::from_cents(6250));
+	assert!(first.scheduled_payment == Money::from_cents(24179));
+	assert!(first.extra_payment == Money::from_cents(10000));
+	assert!(first.ending_balance == Money::from_cents(972071));
+
+	let total_paid: Money = schedule
+		.periods
+		.iter()
+		.flat_map(|p| p.entries.iter())
+		.map(|e| e.scheduled_payment + e.extra_payment)
+		.sum();
+	assert!(total_paid == result.actual_costs_total);
+	assert!(schedule
+		.periods
+		.last()
+		.unwrap()
+		.entries
+		.iter()
+		.all(|e| e.ending_balance.is_zero()));
+	Ok(())
+}
+
+#[test]
+fn observer_receives_events() -> Result<(), loan_payoff::Error> {
+	let loan1 = loan_payoff::Loan {
+		name: "l1".to_owned(),
+		initial_value: Money::from_f64(10000.0),
+		rate: 0.00625, // 7.5% annual
+		number_of_payments: 48,
+		payment_amount: Money::from_f64(241.79),
+		..Default::default()
+	};
+	let loan2 = loan_payoff::Loan {
+		name: "l2".to_owned(),
+		initial_value: Money::from_f64(12000.0),
+		rate: 8.0 / 12.0 / 100.0, // 8% annual
+		number_of_payments: 48,
+		payment_amount: Money::from_f64(292.96),
+		..Default::default()
+	};
+	let loans = vec![&loan1, &loan2];
+
+	let mut observer = RecordingObserver::default();
+	let result = loan_payoff::pay_loans_with_observer(
+		&loans,
+		Money::from_f64(100.0),
+		&FixedOrder(vec![0, 1]),
+		&mut observer,
+	)?;
+	assert!(observer.orderings == vec![vec![0, 1]]);
+	assert!(
+		observer.paid_off
+			== vec![
+				("l1".to_owned(), result.payoff_periods[0]),
+				("l2".to_owned(), result.payoff_periods[1])
+			]
+	);
+
+	let mut observer = RecordingObserver::default();
+	loan_payoff::pay_loans_all_orderings_with_observer(
+		&loans,
+		Money::from_f64(100.0),
+		&mut observer,
+	)?;
+	assert!(observer.orderings == vec![vec![0, 1], vec![1, 0]]);
+	assert!(observer.paid_off.len() == 4);
+	Ok(())
+}
+
+#[test]
+fn builtin_strategies() -> Result<(), loan_payoff::Error> {
+	let loan1 = loan_payoff::Loan {
+		name: "l1".to_owned(),
+		initial_value: Money::from_f64(10000.0),
+		rate: 0.00625, // 7.5% annual
+		number_of_payments: 48,
+		payment_amount: Money::from_f64(241.79),
+		..Default::default()
+	};
+	let loan2 = loan_payoff::Loan {
+		name: "l2".to_owned(),
+		initial_value: Money::from_f64(12000.0),
+		rate: 8.0 / 12.0 / 100.0, // 8% annual
+		number_of_payments: 48,
+		payment_amount: Money::from_f64(292.96),
+		..Default::default()
+	};
+	let loans = vec![&loan1, &loan2];
+	let extra_amount = Money::from_f64(100.0);
+
+	let snowball =
+		loan_payoff::pay_loans_with_strategy(&loans, extra_amount, &loan_payoff::DebtSnowball)?;
+	assert!(snowball.ordering == vec![0, 1]);
+	assert!(snowball.savings_total == Money::from_cents(65450));
+
+	let avalanche =
+		loan_payoff::pay_loans_with_strategy(&loans, extra_amount, &loan_payoff::DebtAvalanche)?;
+	assert!(avalanche.ordering == vec![1, 0]);
+	assert!(avalanche.savings_total == Money::from_cents(68687));
+
+	let highest_payment = loan_payoff::pay_loans_with_strategy(
+		&loans,
+		extra_amount,
+		&loan_payoff::HighestPaymentFirst,
+	)?;
+	assert!(highest_payment == avalanche);
+
+	let fixed = loan_payoff::pay_loans_with_strategy(&loans, extra_amount, &FixedOrder(vec![1]));
+	assert!(matches!(fixed, Err(loan_payoff::Error::OtherError(_))));
+	Ok(())
+}
+
+// Loan with the payment amount worked out from the other fields
+fn amortized_loan(name: &str, initial_value: f64, rate: f64, number_of_payments: i64) -> Loan {
+	let mut loan = Loan::new(
+		name.to_owned(),
+		Money::from_f64(initial_value),
+		rate,
+		number_of_payments,
+		Money::ZERO,
+	);
+	loan.payment_amount = Money::from_f64(loan.calculate_payment_amount());
+	loan
+}
+
+#[test]
+fn optimal_payoff_matches_all_orderings() -> Result<(), loan_payoff::Error> {
+	let mut loan_sets = vec![
+		vec![
+			amortized_loan("num1", 10000.0, 0.023, 23),
+			amortized_loan("num2", 10000.0, 0.00625, 48),
+			amortized_loan("num3", 13000.0, 0.014, 48),
+		],
+		vec![
+			amortized_loan("l1", 10000.0, 0.00625, 48),
+			amortized_loan("l2", 12000.0, 8.0 / 12.0 / 100.0, 48),
+		],
+	];
+
+	// small linear congruential generator so the loan sets are the same every run
+	let mut seed: u64 = 42;
+	let mut next = |max: u64| {
+		seed = seed
+			.wrapping_mul(6364136223846793005)
+			.wrapping_add(1442695040888963407);
+		(seed >> 33) % max
+	};
+	for size in 1..=6 {
+		for _ in 0..4 {
+			let loans = (0..size)
+				.map(|i| {
+					amortized_loan(
+						&format!("loan{}", i),
+						(1000 + next(20) * 1000) as f64,
+						(1 + next(24)) as f64 / 1200.0,
+						(6 + next(10) * 6) as i64,
+					)
+				})
+				.collect();
+			loan_sets.push(loans);
+		}
+	}
+
+	for loans in loan_sets.iter() {
+		let loans: Vec<&Loan> = loans.iter().collect();
+		let extra_payments = [
+			ExtraPayments::constant(Money::ZERO),
+			ExtraPayments::constant(Money::from_f64(100.0)),
+			ExtraPayments::constant(Money::from_f64(750.0)),
+			ExtraPayments::ranges(vec![
+				ExtraPaymentRange {
+					first_period: 7,
+					last_period: Some(18),
+					amount: Money::from_f64(200.0),
+				},
+				ExtraPaymentRange {
+					first_period: 13,
+					last_period: None,
+					amount: Money::from_f64(400.0),
+				},
+			]),
+			ExtraPayments::growing(Money::from_f64(100.0), 0.25),
+			ExtraPayments::constant(Money::from_f64(100.0)).with_lump_sums([
+				LumpSum::once(5, Money::from_f64(3000.0)),
+				LumpSum::yearly(12, Money::from_f64(1500.0)),
+			]),
+		];
+		for extra_payments in extra_payments.iter() {
+			let all_orderings =
+				match loan_payoff::pay_loans_all_orderings(&loans, extra_payments.clone()) {
+					Err(loan_payoff::Error::LoanGoesToInf) => {
+						// rounding can leave a last cent that no ordering pays off in time
+						assert!(matches!(
+							loan_payoff::optimal_payoff(&loans, extra_payments.clone()),
+							Err(loan_payoff::Error::LoanGoesToInf)
+						));
+						continue;
+					}
+					result => result?,
+				};
+			let optimal = loan_payoff::optimal_payoff(&loans, extra_payments.clone())?;
+
+			assert!(optimal.best.savings_total == all_orderings.best.savings_total);
+			assert!(
+				optimal.best
+					== loan_payoff::pay_loans(
+						&loans,
+						extra_payments.clone(),
+						&optimal.best.ordering
+					)?
+			);
+			assert!(
+				optimal.debt_snowball.map(|s| s.savings_total)
+					== all_orderings.debt_snowball.map(|s| s.savings_total)
+			);
+			assert!(optimal.savings_over_debt_snowball == all_orderings.savings_over_debt_snowball);
+		}
+	}
+	Ok(())
+}
+
+#[test]
+fn optimal_payoff_finds_best_ordering() -> Result<(), loan_payoff::Error> {
+	let loan1 = amortized_loan("num1", 10000.0, 0.023, 23);
+	let loan2 = amortized_loan("num2", 10000.0, 0.00625, 48);
+	let loan3 = amortized_loan("num3", 13000.0, 0.014, 48);
+	let loans = vec![&loan1, &loan2, &loan3];
+	let optimal = loan_payoff::optimal_payoff(&loans, Money::from_f64(100.0))?;
+
+	assert!(optimal.best.ordering == vec![0, 2, 1]);
+	assert!(optimal.best.savings_total == Money::from_cents(218246));
+	assert!(optimal.savings_over_debt_snowball == Money::from_cents(29665));
+
+	let mut observer = RecordingObserver::default();
+	loan_payoff::optimal_payoff_with_observer(&loans, Money::from_f64(100.0), &mut observer)?;
+	assert!(observer.orderings.last() == Some(&optimal.best.ordering));
+	Ok(())
+}
+
+#[test]
+fn all_orderings_pick_first_of_ties() -> Result<(), loan_payoff::Error> {
+	// more orderings than the parallel feature hands out at once, with identical loans to make ties
+	let loans = [
+		amortized_loan("a", 5000.0, 0.01, 36),
+		amortized_loan("b", 5000.0, 0.01, 36),
+		amortized_loan("c", 8000.0, 0.005, 60),
+		amortized_loan("d", 8000.0, 0.005, 60),
+		amortized_loan("e", 3000.0, 0.015, 24),
+		amortized_loan("f", 12000.0, 0.004, 72),
+		amortized_loan("g", 2000.0, 0.02, 12),
+	];
+	let loans: Vec<&Loan> = loans.iter().collect();
+	let extra_amount = Money::from_f64(100.0);
+
+	let all_orderings = loan_payoff::pay_loans_all_orderings(&loans, extra_amount)?;
+	let one_at_a_time = loan_payoff::pay_loans_all_orderings_with_observer(
+		&loans,
+		extra_amount,
+		&mut loan_payoff::SilentObserver,
+	)?;
+	assert!(all_orderings.best == one_at_a_time.best);
+	assert!(all_orderings.debt_snowball == one_at_a_time.debt_snowball);
+	assert!(all_orderings.savings_over_debt_snowball == one_at_a_time.savings_over_debt_snowball);
+	Ok(())
+}
+
+#[test]
+fn leftover_of_a_paid_off_loan_flows_on() -> Result<(), loan_payoff::Error> {
+	let small = amortized_loan("small", 300.0, 0.01, 12);
+	let car = amortized_loan("car", 8000.0, 0.006, 36);
+	let loans = vec![&small, &car];
+	let schedule = loan_payoff::amortization_schedule(
+		&loans,
+		Money::from_f64(400.0),
+		&FixedOrder(vec![0, 1]),
+	)?;
+	let first = &schedule.periods[0].entries;
+	assert!(first[0].ending_balance.is_zero());
+	let paid_off = first[0].scheduled_payment + first[0].extra_payment;
+	assert!(paid_off == Money::from_f64(303.0));
+	// whatever the small loan didn't need of its payment and the extra goes on to the car
+	assert!(first[1].scheduled_payment == car.payment_amount);
+	assert!(first[1].extra_payment == small.payment_amount + Money::from_f64(400.0) - paid_off);
+	Ok(())
+}
+
+#[test]
+fn extra_payments_schedule() -> Result<(), loan_payoff::Error> {
+	let loan1 = amortized_loan("l1", 10000.0, 0.00625, 48);
+	let loan2 = amortized_loan("l2", 12000.0, 0.02083, 36);
+	let loans = vec![&loan1, &loan2];
+	let ordering = vec![1, 0];
+
+	let constant = loan_payoff::pay_loans(&loans, Money::from_f64(100.0), &ordering)?;
+	let same = loan_payoff::pay_loans(
+		&loans,
+		ExtraPayments::ranges(vec![ExtraPaymentRange {
+			first_period: 1,
+			last_period: None,
+			amount: Money::from_f64(100.0),
+		}]),
+		&ordering,
+	)?;
+	assert!(same == constant);
+
+	// nothing extra for the first year, then more than before
+	let later = ExtraPayments::ranges(vec![ExtraPaymentRange {
+		first_period: 13,
+		last_period: None,
+		amount: Money::from_f64(150.0),
+	}]);
+	let schedule =
+		loan_payoff::amortization_schedule(&loans, later.clone(), &FixedOrder(ordering.clone()))?;
+	assert!(schedule.periods[..12]
+		.iter()
+		.flat_map(|p| p.entries.iter())
+		.all(|e| e.extra_payment.is_zero()));
+	assert!(schedule.periods[12].entries[0].extra_payment == Money::from_f64(150.0));
+	let later = loan_payoff::pay_loans(&loans, later, &ordering)?;
+	assert!(later.savings_total < constant.savings_total);
+	assert!(later.savings_total.is_positive());
+
+	let growing = loan_payoff::pay_loans(
+		&loans,
+		ExtraPayments::growing(Money::from_f64(100.0), 0.1),
+		&ordering,
+	)?;
+	assert!(growing.savings_total > constant.savings_total);
+	Ok(())
+}
+
+#[test]
+fn lump_sums() -> Result<(), loan_payoff::Error> {
+	let loan1 = amortized_loan("l1", 10000.0, 0.00625, 48);
+	let loan2 = amortized_loan("l2", 2000.0, 0.02083, 36);
+	let loans = vec![&loan1, &loan2];
+	let ordering = vec![1, 0];
+	let extra_payments = ExtraPayments::constant(Money::from_f64(100.0))
+		.with_lump_sums([LumpSum::once(3, Money::from_f64(3000.0))]);
+
+	let schedule = loan_payoff::amortization_schedule(
+		&loans,
+		extra_payments.clone(),
+		&FixedOrder(ordering.clone()),
+	)?;
+	// the lump sum pays off l2 and the rest goes to l1
+	let period = &schedule.periods[2];
+	let l2 = period.entries.iter().find(|e| e.loan == 1).unwrap();
+	let l1 = period.entries.iter().find(|e| e.loan == 0).unwrap();
+	assert!(l2.ending_balance.is_zero());
+	assert!(l1.extra_payment.is_positive());
+	let paid: Money = period
+		.entries
+		.iter()
+		.map(|e| e.scheduled_payment + e.extra_payment)
+		.sum();
+	assert!(paid == loan1.payment_amount + loan2.payment_amount + Money::from_f64(3100.0));
+
+	let with_lump_sum = loan_payoff::pay_loans(&loans, extra_payments, &ordering)?;
+	let without = loan_payoff::pay_loans(&loans, Money::from_f64(100.0), &ordering)?;
+	assert!(with_lump_sum.payoff_periods[1] == 3);
+	assert!(with_lump_sum.savings_total > without.savings_total);
+	assert!(with_lump_sum.total_periods < without.total_periods);
+	Ok(())
+}
+
+#[test]
+fn split_extra_payment() -> Result<(), loan_payoff::Error> {
+	let loan1 = amortized_loan("l1", 10000.0, 0.00625, 48);
+	let loan2 = amortized_loan("l2", 2000.0, 0.02083, 36);
+	let loans = vec![&loan1, &loan2];
+	let split = Split {
+		allocation: Allocation::new(vec![AllocationRule {
+			loan: 1,
+			share: Share::Percentage(0.25),
+		}]),
+		then: Box::new(FixedOrder(vec![0, 1])),
+	};
+
+	let schedule = loan_payoff::amortization_schedule(&loans, Money::from_f64(100.0), &split)?;
+	let period = &schedule.periods[0];
+	let l1 = period.entries.iter().find(|e| e.loan == 0).unwrap();
+	let l2 = period.entries.iter().find(|e| e.loan == 1).unwrap();
+	assert!(l1.extra_payment == Money::from_f64(75.0));
+	assert!(l2.extra_payment == Money::from_f64(25.0));
+
+	let result = loan_payoff::pay_loans_with_strategy(&loans, Money::from_f64(100.0), &split)?;
+	let unsplit = loan_payoff::pay_loans(&loans, Money::from_f64(100.0), &[0, 1])?;
+	assert!(result.allocation == split.allocation);
+	assert!(unsplit.allocation.is_empty());
+	assert!(result.payoff_periods[1] < unsplit.payoff_periods[1]);
+	Ok(())
+}
+
+fn orderings(n: usize) -> Vec<Vec<usize>> {
+	if n == 0 {
+		return vec![Vec::new()];
+	}
+	let mut all = Vec::new();
+	for ordering in orderings(n - 1) {
+		for i in 0..=ordering.len() {
+			let mut ordering = ordering.clone();
+			ordering.insert(i, n - 1);
+			all.push(ordering);
+		}
+	}
+	all
+}
+
+#[test]
+fn optimal_payoff_searches_allocations() -> Result<(), loan_payoff::Error> {
+	let loan_sets = [
+		vec![
+			amortized_loan("num1", 10000.0, 0.023, 23),
+			amortized_loan("num2", 10000.0, 0.00625, 48),
+			amortized_loan("num3", 13000.0, 0.014, 48),
+		],
+		vec![
+			amortized_loan("l1", 10000.0, 0.00625, 48),
+			amortized_loan("l2", 2000.0, 0.02083, 36),
+		],
+		vec![
+			amortized_loan("a", 4000.0, 0.015, 24),
+			amortized_loan("b", 9000.0, 0.004, 60),
+			amortized_loan("c", 1500.0, 0.02, 12),
+			amortized_loan("d", 6000.0, 0.01, 36),
+		],
+	];
+
+	for loans in loan_sets.iter() {
+		let loans: Vec<&Loan> = loans.iter().collect();
+		let mut allocations = Allocation::percentage_splits(loans.len(), 0.5);
+		allocations.push(Allocation::new(vec![AllocationRule {
+			loan: 1,
+			share: Share::Fixed(Money::from_f64(60.0)),
+		}]));
+
+		let mut best: Option<PayoffResult> = None;
+		for allocation in allocations.iter() {
+			for ordering in orderings(loans.len()) {
+				let split = Split {
+					allocation: allocation.clone(),
+					then: Box::new(FixedOrder(ordering)),
+				};
+				let result =
+					loan_payoff::pay_loans_with_strategy(&loans, Money::from_f64(250.0), &split)?;
+				if best
+					.as_ref()
+					.is_none_or(|b| result.savings_total > b.savings_total)
+				{
+					best = Some(result);
+				}
+			}
+		}
+		let best = best.unwrap();
+
+		let optimal = loan_payoff::optimal_payoff_with_allocations(
+			&loans,
+			Money::from_f64(250.0),
+			&allocations,
+			&mut SilentObserver,
+		)?;
+		let unsplit = loan_payoff::optimal_payoff(&loans, Money::from_f64(250.0))?;
+		assert!(optimal.best.savings_total == best.savings_total);
+		assert!(optimal.best.savings_total >= unsplit.best.savings_total);
+		assert!(
+			optimal.debt_snowball.map(|s| s.savings_total)
+				== unsplit.debt_snowball.map(|s| s.savings_total)
+		);
+	}
+	Ok(())
+}
+
+#[test]
+fn mixed_payment_frequencies() -> Result<(), loan_payoff::Error> {
+	let mut car = Loan {
+		name: "car".to_owned(),
+		initial_value: Money::from_f64(10000.0),
+		rate: 0.005,
+		number_of_payments: 130,
+		compounding: Compounding::Daily,
+		payment_frequency: PaymentFrequency::Biweekly,
+		..Default::default()
+	};
+	car.payment_amount = Money::from_f64(car.calculate_payment_amount());
+	let mortgage = amortized_loan("mortgage", 20000.0, 0.004, 60);
+	let card = amortized_loan("card", 3000.0, 0.015, 24);
+	let loans = vec![&car, &mortgage, &card];
+	let extra_amount = Money::from_f64(100.0);
+
+	let optimal = loan_payoff::optimal_payoff(&loans, extra_amount)?;
+	let all_orderings = loan_payoff::pay_loans_all_orderings(&loans, extra_amount)?;
+	assert!(optimal.best.periods_per_year == 52);
+	assert!(optimal.best.savings_total == all_orderings.best.savings_total);
+	assert!(optimal.best.savings_total.is_positive());
+
+	let schedule =
+		loan_payoff::amortization_schedule(&loans, extra_amount, &FixedOrder(vec![2, 0, 1]))?;
+	let scheduled = |period: usize, loan: usize| {
+		schedule.periods[period]
+			.entries
+			.iter()
+			.find(|e| e.loan == loan)
+			.unwrap()
+			.scheduled_payment
+	};
+	// the car is paid every other week, the mortgage in the week each month ends
+	assert!(scheduled(0, 0).is_zero());
+	assert!(scheduled(1, 0) == car.payment_amount);
+	assert!(scheduled(3, 1).is_zero());
+	assert!(scheduled(4, 1) == mortgage.payment_amount);
+	// $100 a month is spread across the weeks
+	let first_year_extra: Money = schedule.periods[..52]
+		.iter()
+		.flat_map(|p| p.entries.iter())
+		.map(|e| e.extra_payment)
+		.sum();
+	assert!(first_year_extra == Money::from_f64(23.08) * 52);
+	Ok(())
+}
+
+fn adjustable_loan(on_change: PaymentOnRateChange) -> Loan {
+	let mut loan = amortized_loan("arm", 20000.0, 0.004, 60);
+	loan.rate_schedule = Some(RateSchedule {
+		changes: vec![
+			RateChange {
+				payment: 13,
+				rate: 0.0075,
+			},
+			RateChange {
+				payment: 37,
+				rate: 0.005,
+			},
+		],
+		cap: Some(0.0065),
+		floor: None,
+		on_change,
+	});
+	loan
+}
+
+#[test]
+fn variable_rate_loans() -> Result<(), loan_payoff::Error> {
+	let reamortized = adjustable_loan(PaymentOnRateChange::Reamortize);
+	let loans = vec![&reamortized];
+	let result = loan_payoff::pay_loans(&loans, Money::ZERO, &[0])?;
+	assert!(result.payoff_periods[0] == 60);
+	assert!(result.savings_total.abs() <= Money::from_f64(0.05));
+	assert!(result.expected_costs[0] > reamortized.payment_amount * 60);
+
+	let schedule = loan_payoff::amortization_schedule(&loans, Money::ZERO, &FixedOrder(vec![0]))?;
+	let payment = |period: usize| schedule.periods[period].entries[0].scheduled_payment;
+	let interest = |period: usize| schedule.periods[period].entries[0].interest;
+	assert!(payment(11) == reamortized.payment_amount);
+	// capped at 0.0065, then down to 0.005
+	assert!(payment(12) > payment(11));
+	assert!(
+		interest(12)
+			== schedule.periods[12].entries[0]
+				.starting_balance
+				.mul_rate(0.0065)
+	);
+	assert!(payment(36) < payment(35));
+
+	let kept = adjustable_loan(PaymentOnRateChange::KeepPayment);
+	let loans = vec![&kept];
+	let result = loan_payoff::pay_loans(&loans, Money::ZERO, &[0])?;
+	assert!(result.payoff_periods[0] > 60);
+	assert!(result.expected_costs[0] == result.actual_costs[0]);
+
+	let fixed = amortized_loan("fixed", 8000.0, 0.006, 36);
+	let card = amortized_loan("card", 3000.0, 0.015, 24);
+	for loans in [
+		vec![&reamortized, &fixed, &card],
+		vec![&kept, &fixed, &card],
+	] {
+		for extra_amount in [Money::ZERO, Money::from_f64(150.0), Money::from_f64(900.0)] {
+			let optimal = loan_payoff::optimal_payoff(&loans, extra_amount)?;
+			let all_orderings = loan_payoff::pay_loans_all_orderings(&loans, extra_amount)?;
+			assert!(optimal.best.savings_total == all_orderings.best.savings_total);
+		}
+	}
+	Ok(())
+}
+
+#[test]
+fn deferred_interest_promotion() -> Result<(), loan_payoff::Error> {
+	let card = amortized_loan("card", 3000.0, 0.02, 24);
+	let mut furniture = Loan::new(
+		"furniture".to_owned(),
+		Money::from_f64(2400.0),
+		0.02,
+		24,
+		Money::from_f64(100.0),
+	);
+	furniture.promotion = Some(Promotion {
+		payments: 12,
+		rate: 0.0,
+		deferred_interest: true,
+	});
+	let loans = vec![&card, &furniture];
+
+	// only the regular payments, so the balance is still there when the promotion ends
+	let regular = loan_payoff::pay_loans(&loans, Money::ZERO, &[0, 1])?;
+	assert!(regular.actual_costs[1] > Money::from_f64(2400.0 + 400.0));
+
+	let extra_amount = Money::from_f64(150.0);
+	let card_first = loan_payoff::pay_loans(&loans, extra_amount, &[0, 1])?;
+	assert!(card_first.actual_costs[1] > Money::from_f64(2400.0));
+
+	let all_orderings = loan_payoff::pay_loans_all_orderings(&loans, extra_amount)?;
+	let optimal = loan_payoff::optimal_payoff(&loans, extra_amount)?;
+	assert!(optimal.best.promotion_deadlines);
+	assert!(optimal.best.savings_total > all_orderings.best.savings_total);
+	// cleared just in time, so no interest at all
+	assert!(optimal.best.actual_costs[1] == Money::from_f64(2400.0));
+	assert!(optimal.best.payoff_periods[1] <= 12);
+	Ok(())
+}
+
+#[test]
+fn avalanche_follows_promotional_rates() -> Result<(), loan_payoff::Error> {
+	let mut store = Loan::new(
+		"store".to_owned(),
+		Money::from_f64(3000.0),
+		0.25 / 12.0,
+		36,
+		Money::from_f64(120.0),
+	);
+	store.promotion = Some(Promotion {
+		payments: 12,
+		rate: 0.0,
+		deferred_interest: false,
+	});
+	let card = amortized_loan("card", 3000.0, 0.22 / 12.0, 36);
+	let loans = vec![&store, &card];
+	let schedule =
+		loan_payoff::amortization_schedule(&loans, Money::from_f64(50.0), &DebtAvalanche)?;
+	let extra = |period: usize, loan: usize| {
+		schedule.periods[period - 1]
+			.entries
+			.iter()
+			.find(|e| e.loan == loan)
+			.map_or(Money::ZERO, |e| e.extra_payment)
+	};
+	// the card at 22% goes first while the store card is at 0%, and last once it is at 25%
+	assert!(extra(1, 1) == Money::from_f64(50.0));
+	assert!(extra(12, 0).is_zero());
+	assert!(extra(13, 0) == Money::from_f64(50.0));
+	assert!(extra(13, 1).is_zero());
+	Ok(())
+}
+
+#[test]
+fn revolving_credit_card() -> Result<(), loan_payoff::Error> {
+	let card = Loan::revolving(
+		"card".to_owned(),
+		Money::from_f64(3000.0),
+		0.02,
+		MinimumPayment::default(),
+	);
+	assert!(card.payment_amount == Money::from_f64(90.0));
+	let loans = vec![&card];
+
+	// minimum payments only, they shrink with the balance down to the $25 floor
+	let schedule = loan_payoff::amortization_schedule(&loans, Money::ZERO, &FixedOrder(vec![0]))?;
+	let payments: Vec<Money> = schedule
+		.periods
+		.iter()
+		.map(|p| p.entries[0].scheduled_payment)
+		.collect();
+	assert!(payments[0] == Money::from_f64(90.0));
+	assert!(payments.windows(2).all(|w| w[1] <= w[0]));
+	assert!(payments[payments.len() - 2] == Money::from_f64(25.0));
+	let result = loan_payoff::pay_loans(&loans, Money::ZERO, &[0])?;
+	assert!(result.total_periods == schedule.periods.len() as i64);
+	assert!(result.savings_total.is_zero());
+
+	let car = amortized_loan("car", 8000.0, 0.006, 36);
+	let loans = vec![&card, &car];
+	for extra_amount in [Money::ZERO, Money::from_f64(100.0), Money::from_f64(500.0)] {
+		let optimal = loan_payoff::optimal_payoff(&loans, extra_amount)?;
+		let all_orderings = loan_payoff::pay_loans_all_orderings(&loans, extra_amount)?;
+		assert!(optimal.best.savings_total == all_orderings.best.savings_total);
+	}
+	let card_first = loan_payoff::pay_loans(&loans, Money::from_f64(100.0), &[0, 1])?;
+	assert!(card_first.savings(0) > Money::from_f64(1000.0));
+	Ok(())
+}
+
+fn loan_of_kind(
+	name: &str,
+	initial_value: f64,
+	rate: f64,
+	number_of_payments: i64,
+	kind: LoanKind,
+) -> Loan {
+	let mut loan = Loan {
+		kind,
+		..amortized_loan(name, initial_value, rate, number_of_payments)
+	};
+	let amortizing = amortized_loan(name, initial_value, rate, loan.amortization_payments());
+	// rounded up, so the loan isn't left with a few cents after its last payment
+	let payment = amortizing.calculate_payment_amount();
+	loan.payment_amount = Money::from_cents((payment * 100.0).ceil() as i64);
+	loan
+}
+
+#[test]
+fn interest_only_and_balloon_loans() -> Result<(), loan_payoff::Error> {
+	let construction = loan_of_kind(
+		"construction",
+		100000.0,
+		0.005,
+		120,
+		LoanKind::InterestOnly { payments: 24 },
+	);
+	let loans = vec![&construction];
+	let schedule = loan_payoff::amortization_schedule(&loans, Money::ZERO, &FixedOrder(vec![0]))?;
+	let entry = |period: usize| &schedule.periods[period].entries[0];
+	assert!(entry(0).scheduled_payment == Money::from_f64(500.0));
+	assert!(entry(23).ending_balance == construction.initial_value);
+	assert!(entry(24).scheduled_payment == construction.payment_amount);
+	assert!(schedule.periods.len() == 120);
+
+	// extra principal during the interest-only payments lowers the payment afterwards
+	let schedule =
+		loan_payoff::amortization_schedule(&loans, Money::from_f64(200.0), &FixedOrder(vec![0]))?;
+	let entry = |period: usize| &schedule.periods[period].entries[0];
+	assert!(entry(0).extra_payment == Money::from_f64(200.0));
+	assert!(entry(1).scheduled_payment < Money::from_f64(500.0));
+	assert!(entry(24).scheduled_payment < construction.payment_amount);
+
+	let commercial = loan_of_kind(
+		"commercial",
+		50000.0,
+		0.006,
+		84,
+		LoanKind::Balloon {
+			amortization_payments: 360,
+		},
+	);
+	let loans = vec![&commercial];
+	let result = loan_payoff::pay_loans(&loans, Money::ZERO, &[0])?;
+	assert!(result.payoff_periods[0] == 84);
+	assert!(result.savings_total.is_zero());
+	let schedule = loan_payoff::amortization_schedule(&loans, Money::ZERO, &FixedOrder(vec![0]))?;
+	let balloon = schedule.periods[83].entries[0].scheduled_payment;
+	assert!(balloon > Money::from_f64(40000.0));
+	assert!(schedule.periods[82].entries[0].scheduled_payment == commercial.payment_amount);
+
+	let card = amortized_loan("card", 3000.0, 0.015, 24);
+	let loans = vec![&construction, &commercial, &card];
+	for extra_amount in [Money::ZERO, Money::from_f64(300.0)] {
+		let optimal = loan_payoff::optimal_payoff(&loans, extra_amount)?;
+		let all_orderings = loan_payoff::pay_loans_all_orderings(&loans, extra_amount)?;
+		assert!(optimal.best.savings_total == all_orderings.best.savings_total);
+	}
+	Ok(())
+}
+
+fn student_loan(name: &str, accrual: Accrual) -> Loan {
+	let student = StudentLoan {
+		repayment_start: 13,
+		accrual,
+		capitalization: Vec::new(),
+	};
+	Loan::student(
+		name.to_owned(),
+		Money::from_f64(10000.0),
+		0.005,
+		120,
+		student,
+	)
+}
+
+#[test]
+fn student_loans() -> Result<(), loan_payoff::Error> {
+	let unsubsidized = student_loan("unsubsidized", Accrual::Unsubsidized);
+	let subsidized = student_loan("subsidized", Accrual::Subsidized);
+	assert!(unsubsidized.payment_amount > subsidized.payment_amount);
+
+	let loans = vec![&unsubsidized];
+	let schedule = loan_payoff::amortization_schedule(&loans, Money::ZERO, &FixedOrder(vec![0]))?;
+	let entry = |period: usize| &schedule.periods[period].entries[0];
+	assert!(entry(0).scheduled_payment.is_zero());
+	assert!(entry(11).ending_balance == Money::from_f64(10600.0));
+	assert!(entry(12).scheduled_payment == unsubsidized.payment_amount);
+	let result = loan_payoff::pay_loans(&loans, Money::ZERO, &[0])?;
+	assert!(result.payoff_periods[0] == 132);
+	assert!(result.savings_total.is_zero());
+
+	// paying the interest as it accrues keeps it from being capitalized
+	let schedule =
+		loan_payoff::amortization_schedule(&loans, Money::from_f64(50.0), &FixedOrder(vec![0]))?;
+	assert!(schedule.periods[11].entries[0].ending_balance == Money::from_f64(10000.0));
+
+	// enough extra that the card is paid off with some of the deferment left
+	let card = amortized_loan("card", 2000.0, 0.01, 24);
+	let loans = vec![&subsidized, &unsubsidized, &card];
+	let extra_amount = Money::from_f64(300.0);
+	let subsidized_first = loan_payoff::pay_loans(&loans, extra_amount, &[2, 0, 1])?;
+	let unsubsidized_first = loan_payoff::pay_loans(&loans, extra_amount, &[2, 1, 0])?;
+	assert!(unsubsidized_first.savings_total > subsidized_first.savings_total);
+	let optimal = loan_payoff::optimal_payoff(&loans, extra_amount)?;
+	let all_orderings = loan_payoff::pay_loans_all_orderings(&loans, extra_amount)?;
+	assert!(optimal.best.savings_total == all_orderings.best.savings_total);
+	assert!(
+		optimal.best.ordering.iter().position(|&i| i == 1)
+			< optimal.best.ordering.iter().position(|&i| i == 0)
+	);
+	Ok(())
+}
+
+#[test]
+fn lump_sum_during_deferment() -> Result<(), loan_payoff::Error> {
+	let unsubsidized = student_loan("unsubsidized", Accrual::Unsubsidized);
+	let loans = vec![&unsubsidized];
+	let extra_payments = ExtraPayments::constant(Money::ZERO)
+		.with_lump_sums([LumpSum::once(3, Money::from_f64(10000.0))]);
+	let schedule =
+		loan_payoff::amortization_schedule(&loans, extra_payments, &FixedOrder(vec![0]))?;
+	// the lump sum clears the $150 of interest accrued so far first, the rest of it keeps accruing
+	assert!(schedule.periods[2].entries[0].ending_balance == Money::from_f64(150.0));
+	assert!(schedule.periods[3].entries[0].interest == Money::from_f64(0.75));
+	assert!(schedule.periods[3].entries[0].ending_balance == Money::from_f64(150.75));
+	Ok(())
+}
+
+#[test]
+fn mortgage_pmi_and_escrow() -> Result<(), loan_payoff::Error> {
+	let mut house = amortized_loan("house", 200000.0, 0.004, 360);
+	house.mortgage = Some(Mortgage {
+		home_value: Money::from_f64(220000.0),
+		pmi: Money::from_f64(100.0),
+		pmi_removal_ltv: 0.8,
+		escrow: Money::from_f64(350.0),
+	});
+	assert!(house.statement_payment() == house.payment_amount + Money::from_f64(450.0));
+	let loans = vec![&house];
+
+	let schedule = loan_payoff::amortization_schedule(&loans, Money::ZERO, &FixedOrder(vec![0]))?;
+	assert!(schedule.periods[0].entries[0].total_payment() == house.statement_payment());
+	let regular = loan_payoff::pay_loans(&loans, Money::ZERO, &[0])?;
+	assert!(regular.savings_total.is_zero());
+	assert!(regular.pmi_periods[0] > 12);
+	assert!(regular.pmi_costs[0] == Money::from_f64(100.0) * regular.pmi_periods[0]);
+	// escrow isn't a cost of the loan
+	let principal_and_interest = regular.expected_costs[0] - regular.pmi_costs[0];
+	assert!((principal_and_interest - house.payment_amount * 360).abs() < Money::from_f64(1.0));
+	let last_pmi = regular.pmi_periods[0] as usize;
+	assert!(schedule.periods[last_pmi].entries[0].pmi.is_zero());
+	assert!(schedule.periods[last_pmi].entries[0].starting_balance <= Money::from_f64(176000.0));
+
+	let prepaid = loan_payoff::pay_loans(&loans, Money::from_f64(500.0), &[0])?;
+	assert!(prepaid.pmi_periods[0] < regular.pmi_periods[0]);
+	assert!(prepaid.pmi_costs[0] < regular.pmi_costs[0]);
+
+	let card = amortized_loan("card", 5000.0, 0.015, 36);
+	let loans = vec![&house, &card];
+	let extra_amount = Money::from_f64(300.0);
+	let optimal = loan_payoff::optimal_payoff(&loans, extra_amount)?;
+	let all_orderings = loan_payoff::pay_loans_all_orderings(&loans, extra_amount)?;
+	assert!(optimal.best.savings_total == all_orderings.best.savings_total);
+	Ok(())
+}
+
+#[test]
+fn prepayment_penalties_and_caps() -> Result<(), loan_payoff::Error> {
+	let mut auto = amortized_loan("auto", 12000.0, 0.01, 48);
+	auto.prepayment = Some(Prepayment {
+		penalty: Some(PrepaymentPenalty {
+			charge: PenaltyCharge::MonthsOfInterest(3),
+			window_months: Some(24),
+		}),
+		annual_cap: None,
+	});
+	let loans = vec![&auto];
+	let regular = loan_payoff::pay_loans(&loans, Money::ZERO, &[0])?;
+	assert!(regular.penalty_costs[0].is_zero());
+
+	// paid off in the first two years, so three months of interest on what was left
+	let early = loan_payoff::pay_loans(&loans, Money::from_f64(800.0), &[0])?;
+	assert!(early.payoff_periods[0] <= 24);
+	assert!(early.penalty_costs[0].is_positive());
+	let schedule =
+		loan_payoff::amortization_schedule(&loans, Money::from_f64(800.0), &FixedOrder(vec![0]))?;
+	let last = &schedule.periods[early.payoff_periods[0] as usize - 1].entries[0];
+	assert!(early.penalty_costs[0] == last.starting_balance.mul_rate(0.01) * 3);
+	let no_penalty = Loan {
+		prepayment: None,
+		..auto.clone()
+	};
+	let without = loan_payoff::pay_loans(&[&no_penalty], Money::from_f64(800.0), &[0])?;
+	assert!(without.savings_total - early.savings_total == early.penalty_costs[0]);
+
+	// paid off after the window, no penalty
+	let late = loan_payoff::pay_loans(&loans, Money::from_f64(100.0), &[0])?;
+	assert!(late.payoff_periods[0] > 24);
+	assert!(late.penalty_costs[0].is_zero());
+
+	let mut private = amortized_loan("private", 10000.0, 0.012, 60);
+	private.prepayment = Some(Prepayment {
+		penalty: None,
+		annual_cap: Some(0.2),
+	});
+	let car = amortized_loan("car", 8000.0, 0.005, 48);
+	let loans = vec![&private, &car];
+	let schedule = loan_payoff::amortization_schedule(
+		&loans,
+		Money::from_f64(500.0),
+		&FixedOrder(vec![0, 1]),
+	)?;
+	let extra_in_year = |loan: usize, year: i64| -> Money {
+		schedule
+			.entries_for_loan(loan)
+			.filter(|(period, _)| (period - 1) / 12 == year)
+			.map(|(_, e)| e.extra_payment)
+			.sum()
+	};
+	// 20% of $10,000 a year, the rest of the $6,000 goes to the car
+	assert!(extra_in_year(0, 0) == Money::from_f64(2000.0));
+	assert!(extra_in_year(1, 0) == Money::from_f64(4000.0));
+	assert!(extra_in_year(0, 1) == Money::from_f64(2000.0));
+
+	for extra_amount in [Money::ZERO, Money::from_f64(250.0), Money::from_f64(500.0)] {
+		for loans in [vec![&private, &car], vec![&auto, &private, &car]] {
+			let optimal = loan_payoff::optimal_payoff(&loans, extra_amount)?;
+			let all_orderings = loan_payoff::pay_loans_all_orderings(&loans, extra_amount)?;
+			assert!(optimal.best.savings_total == all_orderings.best.savings_total);
+		}
+	}
+	Ok(())
+}
+
+#[test]
+fn loan_fees() -> Result<(), loan_payoff::Error> {
+	let mut personal = amortized_loan("personal", 6000.0, 0.01, 36);
+	personal.fees = vec![
+		Fee {
+			amount: Money::from_f64(10.0),
+			frequency: FeeFrequency::Monthly,
+		},
+		Fee {
+			amount: Money::from_f64(95.0),
+			frequency: FeeFrequency::Annual { month: 1 },
+		},
+		Fee {
+			amount: Money::from_f64(39.0),
+			frequency: FeeFrequency::Months(vec![5]),
+		},
+	];
+	let loans = vec![&personal];
+	let regular = loan_payoff::pay_loans(&loans, Money::ZERO, &[0])?;
+	assert!(regular.fee_costs[0] == Money::from_f64(36.0 * 10.0 + 3.0 * 95.0 + 39.0));
+	assert!(regular.savings_total.is_zero());
+
+	let early = loan_payoff::pay_loans(&loans, Money::from_f64(400.0), &[0])?;
+	assert!(early.payoff_periods[0] <= 12);
+	assert!(
+		early.fee_costs[0]
+			== Money::from_f64(10.0) * early.payoff_periods[0] + Money::from_f64(95.0 + 39.0)
+	);
+	// the fees that stopped count as savings, give or take the rounding of the last regular payment
+	let no_fees = Loan {
+		fees: Vec::new(),
+		..personal.clone()
+	};
+	let without = loan_payoff::pay_loans(&[&no_fees], Money::from_f64(400.0), &[0])?;
+	let fees_saved = regular.fee_costs[0] - early.fee_costs[0];
+	assert!(
+		(early.savings_total - without.savings_total - fees_saved).abs() < Money::from_f64(1.0)
+	);
+
+	let schedule = loan_payoff::amortization_schedule(&loans, Money::ZERO, &FixedOrder(vec![0]))?;
+	assert!(schedule.periods[0].entries[0].fees == Money::from_f64(105.0));
+	assert!(schedule.periods[4].entries[0].fees == Money::from_f64(49.0));
+
+	let card = amortized_loan("card", 4000.0, 0.01, 36);
+	let loans = vec![&personal, &card];
+	for extra_amount in [Money::from_f64(50.0), Money::from_f64(200.0)] {
+		let optimal = loan_payoff::optimal_payoff(&loans, extra_amount)?;
+		let all_orderings = loan_payoff::pay_loans_all_orderings(&loans, extra_amount)?;
+		assert!(optimal.best.savings_total == all_orderings.best.savings_total);
+	}
+	// at the same rate, the fees make the personal loan the one to pay first
+	let optimal = loan_payoff::optimal_payoff(&loans, Money::from_f64(200.0))?;
+	assert!(optimal.best.ordering == vec![0, 1]);
+	Ok(())
+}
+
+#[test]
+fn extra_payment_to_be_debt_free_by_target() -> Result<(), loan_payoff::Error> {
+	let car = amortized_loan("car", 8000.0, 0.006, 48);
+	let card = amortized_loan("card", 3000.0, 0.015, 36);
+	let loans = vec![&car, &card];
+
+	let required = loan_payoff::required_extra_payment(&loans, 24, &DebtAvalanche)?;
+	assert!(required.result.total_periods <= 24);
+	let result =
+		loan_payoff::pay_loans_with_strategy(&loans, required.extra_amount, &DebtAvalanche)?;
+	assert!(result == required.result);
+	let cent_less = required.extra_amount - Money::from_cents(1);
+	let result = loan_payoff::pay_loans_with_strategy(&loans, cent_less, &DebtAvalanche)?;
+	assert!(result.total_periods > 24);
+
+	// a bonus each year means less is needed every month
+	let with_bonus = loan_payoff::required_extra_payment_with(&loans, 24, |extra| {
+		let extra_payments = ExtraPayments::constant(extra)
+			.with_lump_sums([LumpSum::yearly(12, Money::from_f64(1000.0))]);
+		loan_payoff::optimal_payoff(&loans, extra_payments).map(|optimal| optimal.best)
+	})?;
+	assert!(with_bonus.extra_amount < required.extra_amount);
+	assert!(with_bonus.result.total_periods <= 24);
+	Ok(())
+}
+
+#[test]
+fn loans_with_a_missing_term() -> Result<(), loan_payoff::Error> {
+	let full = amortized_loan("car", 12500.0, 0.00575, 36);
+	let term = |initial_value, rate, number_of_payments, payment_amount| {
+		Loan::from_terms(
+			"car".to_owned(),
+			initial_value,
+			rate,
+			number_of_payments,
+			payment_amount,
+		)
+		.map_err(loan_payoff::Error::OtherError)
+	};
+	let solved = [
+		term(None, Some(full.rate), Some(36), Some(full.payment_amount))?,
+		term(
+			Some(full.initial_value),
+			None,
+			Some(36),
+			Some(full.payment_amount),
+		)?,
+		term(
+			Some(full.initial_value),
+			Some(full.rate),
+			None,
+			Some(full.payment_amount),
+		)?,
+		term(Some(full.initial_value), Some(full.rate), Some(36), None)?,
+	];
+	let expected = loan_payoff::pay_loans(&[&full], Money::from_f64(100.0), &[0])?;
+	for loan in solved.iter() {
+		assert!(loan.number_of_payments == 36);
+		// every solved loan passes validation and pays off the same way, give or take the rounding
+		let result = loan_payoff::pay_loans(&[loan], Money::from_f64(100.0), &[0])?;
+		assert!(result.payoff_periods == expected.payoff_periods);
+		assert!(
+			(result.actual_costs_total - expected.actual_costs_total).abs() < Money::from_f64(1.0)
+		);
+	}
+	Ok(())
+}
+
+#[test]
+fn invalid_loans_report_every_issue() {
+	let good = amortized_loan("good", 5000.0, 0.01, 24);
+	let mut bad = amortized_loan("bad", 8000.0, 0.006, 36);
+	bad.payment_amount = Money::from_f64(240.0);
+	let result = loan_payoff::pay_loans(&[&good, &bad], Money::from_f64(100.0), &[0, 1]);
+	match result {
+		Err(loan_payoff::Error::InvalidLoan {
+			index,
+			ref name,
+			ref issues,
+		}) => {
+			assert!(index == 1 && name == "bad");
+			assert!(issues.len() == 1);
+			assert!(issues[0].field == "payment_amount");
+			assert!(issues[0].actual == "240.00");
+			assert!(issues[0].expected == "247.75 give or take 5 cents");
+		}
+		_ => panic!("expected the bad loan to be invalid"),
+	}
+	let message = result.unwrap_err().to_string();
+	assert!(message.starts_with("loan 'bad' is invalid; payment_amount is 240.00"));
+
+	bad.initial_value = Money::ZERO;
+	bad.number_of_payments = 0;
+	let fields: Vec<&str> = bad
+		.validate()
+		.unwrap_err()
+		.iter()
+		.map(|i| i.field)
+		.collect();
+	assert!(fields == vec!["initial_value", "number_of_payments"]);
+	assert!(good.validate().is_ok());
+}
+
+#[test]
+fn stated_payments_are_trued_up_by_the_last_one() -> Result<(), loan_payoff::Error> {
+	let mut car = amortized_loan("car", 8000.0, 0.006, 36);
+	car.payment_amount = Money::from_f64(240.0);
+	assert!(car.validate().is_err());
+	car.validation_policy = ValidationPolicy::Tolerance(Money::from_f64(5.0));
+	assert!(car.validate().is_err());
+	car.validation_policy = ValidationPolicy::Tolerance(Money::from_f64(10.0));
+	assert!(car.validate().is_ok());
+
+	car.validation_policy = ValidationPolicy::TrustStatement;
+	let adjustment = car.final_payment_adjustment().unwrap();
+	assert!(adjustment.is_positive());
+	let regular = loan_payoff::pay_loans(&[&car], Money::ZERO, &[0])?;
+	assert!(regular.payoff_periods[0] == 36);
+	assert!(regular.savings_total.is_zero());
+	// every payment is the stated one but the last, give or take the rounding of the interest
+	let made = regular.final_payment_adjustments[0];
+	assert!((made - adjustment).abs() < Money::from_f64(0.1));
+	assert!(regular.actual_costs_total == car.payment_amount * 36 + made);
+
+	// paid off before the last payment, so there is nothing to true up
+	let early = loan_payoff::pay_loans(&[&car], Money::from_f64(100.0), &[0])?;
+	assert!(early.payoff_periods[0] < 36);
+	assert!(early.savings_total.is_positive());
+	assert!(early.final_payment_adjustments[0].is_zero());
+
+	// paying more than the formula pays it off early, the last payment is smaller
+	car.payment_amount = Money::from_f64(260.0);
+	assert!(car.final_payment_adjustment().unwrap().is_negative());
+	let over = loan_payoff::pay_loans(&[&car], Money::ZERO, &[0])?;
+	assert!(over.payoff_periods[0] < 36);
+	assert!(over.savings_total.is_zero());
+	Ok(())
+}
+
+#[test]
+fn optimal_payoff_with_stated_payments() -> Result<(), loan_payoff::Error> {
+	let mut car = amortized_loan("car", 8000.0, 0.006, 36);
+	car.payment_amount = Money::from_f64(240.0);
+	car.validation_policy = ValidationPolicy::TrustStatement;
+	let mut personal = amortized_loan("personal", 5000.0, 0.01, 24);
+	personal.payment_amount += Money::from_f64(0.6);
+	personal.validation_policy = ValidationPolicy::Tolerance(Money::from_f64(1.0));
+	let mut furniture = amortized_loan("furniture", 3000.0, 0.008, 18);
+	furniture.payment_amount -= Money::from_f64(2.0);
+	furniture.validation_policy = ValidationPolicy::TrustStatement;
+	let card = amortized_loan("card", 2000.0, 0.015, 12);
+	let loans = vec![&car, &personal, &furniture, &card];
+	for extra_amount in [Money::ZERO, Money::from_f64(50.0), Money::from_f64(400.0)] {
+		let optimal = loan_payoff::optimal_payoff(&loans, extra_amount)?;
+		let all_orderings = loan_payoff::pay_loans_all_orderings(&loans, extra_amount)?;
+		assert!(optimal.best.savings_total == all_orderings.best.savings_total);
+		assert!(optimal.best.actual_costs_total == all_orderings.best.actual_costs_total);
+	}
+	Ok(())
+}
+
+#[test]
+fn solved_term_that_isnt_whole() -> Result<(), loan_payoff::Error> {
+	let car = Loan::from_terms(
+		"car".to_owned(),
+		Some(Money::from_f64(10000.0)),
+		Some(0.005),
+		None,
+		Some(Money::from_f64(300.0)),
+	)
+	.map_err(loan_payoff::Error::OtherError)?;
+	assert!(car.number_of_payments == 37);
+	assert!(car.validate().is_ok());
+
+	// the last payment is smaller, and the expected cost counts it that way
+	let regular = loan_payoff::pay_loans(&[&car], Money::ZERO, &[0])?;
+	assert!(regular.payoff_periods[0] == 37);
+	assert!(regular.savings_total.is_zero());
+	assert!(regular.actual_costs[0] > car.payment_amount * 36);
+	assert!(regular.actual_costs[0] < car.payment_amount * 37);
+
+	let early = loan_payoff::pay_loans(&[&car], Money::from_f64(100.0), &[0])?;
+	assert!(early.payoff_periods[0] < 37);
+	assert!(early.savings_total.is_positive());
 	Ok(())
 }